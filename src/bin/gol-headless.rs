@@ -0,0 +1,121 @@
+use std::{error::Error, path::PathBuf, process};
+
+use clap::Parser;
+use game_of_life::{
+    engine::DEFAULT_MEMORY_LIMIT, pattern::Format, rule::file::RuleFile, Backend, Geometry,
+    Pattern, Rule, Topology,
+};
+
+/// Runs a pattern for a number of generations without opening a window.
+#[derive(Parser)]
+struct Args {
+    /// Pattern file to load (RLE, plaintext or Life 1.05/1.06)
+    pattern: PathBuf,
+
+    /// Rule to run, overriding the one in the pattern file
+    #[arg(short, long)]
+    rule: Option<Rule>,
+
+    /// Golly .rule file to run, overriding --rule and the pattern's rule
+    #[arg(long)]
+    rule_file: Option<PathBuf>,
+
+    /// Edge behaviour of bounded backends: torus, torus+N, plane, klein-bottle,
+    /// cross-surface or sphere
+    #[arg(short, long, default_value_t = Topology::Torus)]
+    topology: Topology,
+
+    /// Cell shape: square, hexagonal or triangular
+    #[arg(long, default_value_t = Geometry::Square)]
+    geometry: Geometry,
+
+    /// Number of generations to run
+    #[arg(short, long, default_value_t = 0)]
+    generations: u64,
+
+    /// Grid width, grown to fit the pattern, topology and geometry if needed
+    #[arg(long, default_value_t = 256)]
+    width: u32,
+
+    /// Grid height, grown to fit the pattern, topology and geometry if needed
+    #[arg(long, default_value_t = 256)]
+    height: u32,
+
+    /// Cell storage: dense, packed, hashlife or sparse
+    #[arg(short, long, default_value_t = Backend::Dense)]
+    backend: Backend,
+
+    /// Memory in MiB that HashLife may use before collecting garbage
+    #[arg(long)]
+    memory_limit: Option<usize>,
+
+    /// Compute each generation on a single thread
+    #[arg(long)]
+    serial: bool,
+
+    /// Write the result here instead of stdout, in the format implied by the extension
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let pattern = Pattern::load(&args.pattern)?;
+    let rule = match (&args.rule_file, args.rule, &pattern.rule) {
+        (Some(path), _, _) => {
+            RuleFile::load(path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+                .rule
+        }
+        (None, Some(rule), _) => rule,
+        (None, None, Some(rule)) => rule.parse()?,
+        (None, None, None) => Rule::default(),
+    };
+
+    let width = args.width.max(pattern.width);
+    let height = args.height.max(pattern.height);
+    let (width, height) = args.topology.fit(width, height);
+    let (width, height) = args.geometry.fit(args.topology, width, height);
+    let mut universe = args.backend.create(width, height, args.topology)?;
+    universe.set_geometry(args.geometry)?;
+    universe.set_rule(rule)?;
+    universe.set_parallel(!args.serial);
+    universe.set_memory_limit(
+        args.memory_limit
+            .map_or(DEFAULT_MEMORY_LIMIT, |mib| mib * 1024 * 1024),
+    );
+    let (x, y) = args.geometry.align(
+        ((width - pattern.width) / 2) as i64,
+        ((height - pattern.height) / 2) as i64,
+    );
+    universe.place(&pattern, x, y);
+    universe.step_n(args.generations);
+
+    let mut result = universe.extract()?;
+    result.name = pattern.name;
+    result
+        .comments
+        .push(format!("generation = {}", universe.generation()));
+    result
+        .comments
+        .push(format!("population = {}", universe.population()));
+    if let Some(bbox) = universe.bounding_box() {
+        result.comments.push(format!(
+            "bounding box = {}x{} at ({}, {})",
+            bbox.width, bbox.height, bbox.x, bbox.y
+        ));
+    }
+
+    match args.output {
+        Some(path) => result.save(path)?,
+        None => print!("{}", result.write(Format::Rle)),
+    }
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+    if let Err(e) = run(Args::parse()) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,87 @@
+use game_of_life::{Geometry, Rect};
+
+const MIN_ZOOM: f64 = 2.0;
+const MAX_ZOOM: f64 = 128.0;
+
+/// Maps between window pixels and cell coordinates. `position` is the cell
+/// coordinate at the top-left corner of the window and `zoom` is the size of
+/// a cell in pixels.
+pub struct Camera {
+    pub position: glam::DVec2,
+    pub zoom: f64,
+    pub geometry: Geometry,
+}
+
+impl Camera {
+    pub fn new(zoom: f64, geometry: Geometry) -> Self {
+        Self {
+            position: glam::DVec2::ZERO,
+            zoom: zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+            geometry,
+        }
+    }
+
+    /// Hexagons and triangles reach past their column and row, so cells one
+    /// beyond the edges of the window may show.
+    fn margin(&self) -> i64 {
+        match self.geometry {
+            Geometry::Square => 0,
+            _ => 1,
+        }
+    }
+
+    pub fn screen_to_world(&self, screen: glam::Vec2) -> glam::DVec2 {
+        self.position + screen.as_dvec2() / self.zoom
+    }
+
+    /// The cell under a window position.
+    pub fn screen_to_cell(&self, screen: glam::Vec2) -> (i64, i64) {
+        let world = self.screen_to_world(screen);
+        self.geometry.cell_at(world.x, world.y)
+    }
+
+    /// Scales by `factor`, keeping the point under `screen` fixed.
+    pub fn zoom_at(&mut self, screen: glam::Vec2, factor: f64) {
+        let anchor = self.screen_to_world(screen);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.position = anchor - screen.as_dvec2() / self.zoom;
+    }
+
+    /// Moves the view so that the grid follows a drag of `delta` pixels.
+    pub fn pan(&mut self, delta: glam::Vec2) {
+        self.position -= delta.as_dvec2() / self.zoom;
+    }
+
+    /// Centres `rect` in a `width` by `height` pixel viewport with a margin.
+    pub fn fit(&mut self, rect: Rect, width: u32, height: u32) {
+        let zoom_x = width as f64 / (rect.width as f64 + 2.0);
+        let zoom_y = height as f64 / (rect.height as f64 + 2.0);
+        self.zoom = zoom_x.min(zoom_y).clamp(MIN_ZOOM, MAX_ZOOM);
+        let centre = glam::dvec2(
+            rect.x as f64 + rect.width as f64 / 2.0,
+            rect.y as f64 + rect.height as f64 / 2.0,
+        );
+        self.position = centre - glam::dvec2(width as f64, height as f64) / (2.0 * self.zoom);
+    }
+
+    /// The cells that are at least partly visible in the viewport.
+    pub fn view_rect(&self, width: u32, height: u32) -> Rect {
+        let origin = self.position.floor();
+        let margin = self.margin();
+        Rect {
+            x: origin.x as i64 - margin,
+            y: origin.y as i64 - margin,
+            width: (width as f64 / self.zoom).ceil() as u64 + 1 + 2 * margin as u64,
+            height: (height as f64 / self.zoom).ceil() as u64 + 1 + 2 * margin as u64,
+        }
+    }
+
+    /// Projection from pixels relative to the top-left cell of `view_rect` to clip
+    /// space.
+    pub fn view_matrix(&self, width: u32, height: u32) -> glam::Mat4 {
+        let origin = self.position.floor() - glam::DVec2::splat(self.margin() as f64);
+        let offset = (self.position - origin) * self.zoom;
+        glam::Mat4::orthographic_rh(0.0, width as f32, height as f32, 0.0, 0.0, 100.0)
+            * glam::Mat4::from_translation(glam::vec3(-offset.x as f32, -offset.y as f32, 0.0))
+    }
+}
@@ -1,18 +1,53 @@
+use game_of_life::Geometry;
+
 pub struct Cell {
     pub position: glam::Vec2,
-    pub state: bool,
+    /// Drawn upside down, for triangles that point down.
+    pub flipped: bool,
 }
 
 impl Cell {
+    /// Places a `size` pixel cell, shrunk about its centre to leave a gap to
+    /// its neighbours that shrinks as cells get smaller.
     pub fn model_matrix(&self, size: f32) -> [[f32; 4]; 4] {
+        let gap = (size / 8.0).min(2.0);
+        let scale = size - gap;
+        let (scale_y, offset_y) = if self.flipped {
+            (-scale, gap / 2.0 + scale)
+        } else {
+            (scale, gap / 2.0)
+        };
         glam::Mat4::from_scale_rotation_translation(
-            glam::vec3(size - 2.0, size - 2.0, 1.0),
+            glam::vec3(scale, scale_y, 1.0),
             glam::Quat::IDENTITY,
-            glam::vec3(self.position.x, self.position.y, 0.0),
+            glam::vec3(self.position.x + gap / 2.0, self.position.y + offset_y, 0.0),
         )
         .to_cols_array_2d()
     }
 
+    /// Vertices and triangle indices of a cell one unit wide, laid out as
+    /// `Geometry::cell_at` describes. Triangles point up.
+    pub fn mesh(geometry: Geometry) -> (Vec<[f32; 2]>, Vec<u16>) {
+        match geometry {
+            Geometry::Square => (
+                vec![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]],
+                vec![0, 1, 2, 2, 1, 3],
+            ),
+            Geometry::Hexagonal => (
+                vec![
+                    [0.5, -0.125],
+                    [1.0, 0.125],
+                    [1.0, 0.875],
+                    [0.5, 1.125],
+                    [0.0, 0.875],
+                    [0.0, 0.125],
+                ],
+                vec![0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 5],
+            ),
+            Geometry::Triangular => (vec![[0.5, 0.0], [1.5, 1.0], [-0.5, 1.0]], vec![0, 1, 2]),
+        }
+    }
+
     const MATRIX_ATTRIBS: [wgpu::VertexAttribute; 4] =
         wgpu::vertex_attr_array![2 => Float32x4, 3 => Float32x4, 4 => Float32x4, 5 => Float32x4];
 
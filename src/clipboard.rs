@@ -0,0 +1,42 @@
+use game_of_life::{pattern::Format, Pattern};
+
+/// Copied cells, shared with other applications as RLE text through the
+/// system clipboard when one is available.
+pub struct Clipboard {
+    system: Option<arboard::Clipboard>,
+    pattern: Option<Pattern>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        let system = arboard::Clipboard::new()
+            .map_err(|e| log::warn!("System clipboard unavailable: {}", e))
+            .ok();
+        Self {
+            system,
+            pattern: None,
+        }
+    }
+
+    pub fn copy(&mut self, pattern: Pattern) {
+        if let Some(system) = &mut self.system {
+            if let Err(e) = system.set_text(pattern.write(Format::Rle)) {
+                log::warn!("Failed to copy to the system clipboard: {}", e);
+            }
+        }
+        self.pattern = Some(pattern);
+    }
+
+    /// The pattern on the system clipboard if it holds one, otherwise the
+    /// last pattern copied here.
+    pub fn paste(&mut self) -> Option<Pattern> {
+        let text = self
+            .system
+            .as_mut()
+            .and_then(|system| system.get_text().ok());
+        match text.map(|text| Pattern::parse(&text, Format::detect(&text))) {
+            Some(Ok(pattern)) if pattern.population() > 0 => Some(pattern),
+            _ => self.pattern.clone(),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::{error::Error, fs, path::PathBuf};
+
+use clap::Parser;
+use game_of_life::{engine::DEFAULT_MEMORY_LIMIT, Anchor, Backend, Geometry, Rule, Topology};
+use serde::Deserialize;
+
+use crate::rewind::DEFAULT_REWIND_MEMORY;
+
+const DEFAULT_CONFIG_PATH: &str = "game_of_life.toml";
+
+/// Conway's Game of Life. Flags override values from the config file.
+#[derive(Parser)]
+struct Args {
+    /// TOML config file [default: game_of_life.toml, if present]
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Number of cell columns
+    #[arg(long)]
+    width: Option<u32>,
+
+    /// Number of cell rows
+    #[arg(long)]
+    height: Option<u32>,
+
+    /// Cell size in pixels
+    #[arg(long)]
+    cell_size: Option<u32>,
+
+    /// Birth/survival rule, e.g. B36/S23, B2-a/S12 or R5,C0,M1,S34..58,B34..45,NM
+    #[arg(short, long)]
+    rule: Option<String>,
+
+    /// Golly .rule file to run instead, with its colours
+    #[arg(long)]
+    rule_file: Option<PathBuf>,
+
+    /// Pattern file to load at startup
+    #[arg(short, long)]
+    pattern: Option<PathBuf>,
+
+    /// Seconds between generations
+    #[arg(short, long)]
+    interval: Option<f32>,
+
+    /// Open a borderless fullscreen window
+    #[arg(short, long)]
+    fullscreen: bool,
+
+    /// fifo, mailbox or immediate
+    #[arg(long)]
+    present_mode: Option<String>,
+
+    /// Which point stays fixed when the window is resized: top-left or centre
+    #[arg(long)]
+    resize_anchor: Option<String>,
+
+    /// Resize the grid with the window instead of keeping --width and --height
+    #[arg(long)]
+    fit_window: bool,
+
+    /// Cell storage: dense, packed, hashlife or sparse
+    #[arg(short, long)]
+    backend: Option<String>,
+
+    /// Grid edges: torus, torus+N, plane, klein-bottle, cross-surface or sphere
+    #[arg(short, long)]
+    topology: Option<String>,
+
+    /// Cell shape: square, hexagonal or triangular
+    #[arg(short, long)]
+    geometry: Option<String>,
+
+    /// Memory in MiB that HashLife may use before collecting garbage
+    #[arg(long)]
+    memory_limit: Option<usize>,
+
+    /// Memory in MiB for recent generations that can be rewound to
+    #[arg(long)]
+    rewind_memory: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct FileConfig {
+    width: Option<u32>,
+    height: Option<u32>,
+    cell_size: Option<u32>,
+    rule: Option<String>,
+    rule_file: Option<PathBuf>,
+    pattern: Option<PathBuf>,
+    interval: Option<f32>,
+    fullscreen: Option<bool>,
+    present_mode: Option<String>,
+    resize_anchor: Option<String>,
+    fit_window: Option<bool>,
+    backend: Option<String>,
+    topology: Option<String>,
+    geometry: Option<String>,
+    memory_limit: Option<usize>,
+    rewind_memory: Option<usize>,
+}
+
+pub struct Config {
+    pub width: u32,
+    pub height: u32,
+    pub cell_size: u32,
+    pub rule: Option<Rule>,
+    pub rule_file: Option<PathBuf>,
+    pub pattern: Option<PathBuf>,
+    pub interval: f32,
+    pub fullscreen: bool,
+    pub present_mode: wgpu::PresentMode,
+    pub resize_anchor: Anchor,
+    pub fit_window: bool,
+    pub backend: Backend,
+    pub topology: Topology,
+    pub geometry: Geometry,
+    pub memory_limit: usize,
+    pub rewind_memory: usize,
+}
+
+impl Config {
+    /// Reads the command line and the config file it points to.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let args = Args::parse();
+        let file = match &args.config {
+            Some(path) => Self::read_file(path)?,
+            None if fs::metadata(DEFAULT_CONFIG_PATH).is_ok() => {
+                Self::read_file(&PathBuf::from(DEFAULT_CONFIG_PATH))?
+            }
+            None => FileConfig::default(),
+        };
+
+        let present_mode = match args.present_mode.or(file.present_mode).as_deref() {
+            None | Some("fifo") => wgpu::PresentMode::Fifo,
+            Some("mailbox") => wgpu::PresentMode::Mailbox,
+            Some("immediate") => wgpu::PresentMode::Immediate,
+            Some(mode) => {
+                return Err(format!(
+                    "unknown present mode '{}', expected fifo, mailbox or immediate",
+                    mode
+                )
+                .into())
+            }
+        };
+
+        Ok(Self {
+            width: args.width.or(file.width).unwrap_or(80),
+            height: args.height.or(file.height).unwrap_or(45),
+            cell_size: args.cell_size.or(file.cell_size).unwrap_or(16).max(1),
+            rule: args.rule.or(file.rule).map(|r| r.parse()).transpose()?,
+            rule_file: args.rule_file.or(file.rule_file),
+            pattern: args.pattern.or(file.pattern),
+            interval: args.interval.or(file.interval).unwrap_or(0.2),
+            fullscreen: args.fullscreen || file.fullscreen.unwrap_or(false),
+            present_mode,
+            resize_anchor: match args.resize_anchor.or(file.resize_anchor) {
+                Some(anchor) => anchor.parse()?,
+                None => Anchor::default(),
+            },
+            fit_window: args.fit_window || file.fit_window.unwrap_or(false),
+            backend: match args.backend.or(file.backend) {
+                Some(backend) => backend.parse()?,
+                None => Backend::default(),
+            },
+            topology: match args.topology.or(file.topology) {
+                Some(topology) => topology.parse()?,
+                None => Topology::default(),
+            },
+            geometry: match args.geometry.or(file.geometry) {
+                Some(geometry) => geometry.parse()?,
+                None => Geometry::default(),
+            },
+            memory_limit: args
+                .memory_limit
+                .or(file.memory_limit)
+                .map_or(DEFAULT_MEMORY_LIMIT, |mib| mib * 1024 * 1024),
+            rewind_memory: args
+                .rewind_memory
+                .or(file.rewind_memory)
+                .map_or(DEFAULT_REWIND_MEMORY, |mib| mib * 1024 * 1024),
+        })
+    }
+
+    fn read_file(path: &PathBuf) -> Result<FileConfig, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+}
@@ -0,0 +1,245 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    geometry::Geometry,
+    hashlife::HashLife,
+    packed::PackedUniverse,
+    pattern::{Pattern, PatternError},
+    rule::Rule,
+    sparse::SparseUniverse,
+    universe::{Anchor, Topology, Universe},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i64,
+    pub y: i64,
+    pub width: u64,
+    pub height: u64,
+}
+
+impl Rect {
+    /// The smallest rectangle containing every point, if there are any.
+    pub fn bounding(points: impl IntoIterator<Item = (i64, i64)>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let (x, y) = points.next()?;
+        let (min_x, min_y, max_x, max_y) = points.fold((x, y, x, y), |(x0, y0, x1, y1), (x, y)| {
+            (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+        });
+        Some(Self {
+            x: min_x,
+            y: min_y,
+            width: (max_x - min_x + 1) as u64,
+            height: (max_y - min_y + 1) as u64,
+        })
+    }
+
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        x >= self.x
+            && y >= self.y
+            && ((x - self.x) as u64) < self.width
+            && ((y - self.y) as u64) < self.height
+    }
+}
+
+/// A cell storage and stepping algorithm. `Game` and the headless runner
+/// drive the simulation only through this interface.
+pub trait Engine: Send {
+    fn rule(&self) -> &Rule;
+
+    /// Switches to `rule`, or fails if the engine cannot run it.
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String>;
+
+    /// How the edges of a bounded grid connect, or `None` for an unbounded plane.
+    fn topology(&self) -> Option<Topology>;
+
+    fn geometry(&self) -> Geometry {
+        Geometry::Square
+    }
+
+    /// Switches the shape of the cells, or fails if the engine or its rule
+    /// cannot run on it.
+    fn set_geometry(&mut self, geometry: Geometry) -> Result<(), String> {
+        match geometry {
+            Geometry::Square => Ok(()),
+            _ => Err(format!("only the dense backend runs {} grids", geometry)),
+        }
+    }
+
+    fn generation(&self) -> u64;
+
+    /// Overrides the generation counter, e.g. when restoring a saved state.
+    fn set_generation(&mut self, generation: u64);
+
+    fn get(&self, x: i64, y: i64) -> u32;
+
+    fn set(&mut self, x: i64, y: i64, state: u32);
+
+    fn step(&mut self);
+
+    fn step_n(&mut self, n: u64) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+
+    /// Every non-zero cell as `(x, y, state)`.
+    fn live_cells(&self) -> Vec<(i64, i64, u32)>;
+
+    /// Changes the dimensions of a bounded grid. Unbounded engines ignore it.
+    fn resize(&mut self, _width: u32, _height: u32, _anchor: Anchor) {}
+
+    /// Whether the engine may spread a generation across threads.
+    fn set_parallel(&mut self, _parallel: bool) {}
+
+    /// Approximate memory an engine with caches may use before freeing them.
+    fn set_memory_limit(&mut self, _bytes: usize) {}
+
+    fn population(&self) -> u64 {
+        self.live_cells().len() as u64
+    }
+
+    /// The smallest rectangle containing every live cell, if any.
+    fn bounding_box(&self) -> Option<Rect> {
+        Rect::bounding(self.live_cells().into_iter().map(|(x, y, _)| (x, y)))
+    }
+
+    /// Copies the states of `rect` into `out`, row-major.
+    fn read_region(&self, rect: Rect, out: &mut [u32]) {
+        for (index, state) in out.iter_mut().enumerate() {
+            let x = rect.x + (index as u64 % rect.width) as i64;
+            let y = rect.y + (index as u64 / rect.width) as i64;
+            *state = self.get(x, y);
+        }
+    }
+
+    fn clear(&mut self) {
+        for (x, y, _) in self.live_cells() {
+            self.set(x, y, 0);
+        }
+    }
+
+    fn toggle(&mut self, x: i64, y: i64) {
+        let state = self.get(x, y);
+        self.set(x, y, 1 - state.min(1));
+    }
+
+    /// Writes a pattern's cells with its top-left corner at `(x, y)`.
+    fn place(&mut self, pattern: &Pattern, x: i64, y: i64) {
+        for &(cell_x, cell_y, state) in &pattern.cells {
+            self.set(x + cell_x as i64, y + cell_y as i64, state);
+        }
+    }
+
+    /// Returns the cells of `rect` as a pattern the size of `rect`.
+    fn copy_region(&self, rect: Rect) -> Pattern {
+        let mut states = vec![0; (rect.width * rect.height) as usize];
+        self.read_region(rect, &mut states);
+        let cells = states
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, state)| state > 0)
+            .map(|(index, state)| {
+                let x = (index as u64 % rect.width) as i32;
+                let y = (index as u64 / rect.width) as i32;
+                (x, y, state)
+            })
+            .collect();
+        Pattern {
+            rule: Some(self.rule().to_string()),
+            width: rect.width as u32,
+            height: rect.height as u32,
+            cells,
+            ..Default::default()
+        }
+    }
+
+    fn clear_region(&mut self, rect: Rect) {
+        for (x, y, _) in self.copy_region(rect).cells {
+            self.set(rect.x + x as i64, rect.y + y as i64, 0);
+        }
+    }
+
+    /// Returns the bounding box of all live cells as a pattern, or fails if
+    /// it is too large for one.
+    fn extract(&self) -> Result<Pattern, PatternError> {
+        let mut pattern = Pattern::from_cells(self.live_cells())?;
+        pattern.rule = Some(self.rule().to_string());
+        Ok(pattern)
+    }
+}
+
+/// Which `Engine` implementation to run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// One `u32` per cell.
+    #[default]
+    Dense,
+    /// 64 cells per word, stepped with bit-parallel adders.
+    Packed,
+    /// Memoised quadtree on an unbounded plane.
+    HashLife,
+    /// Hash map of bit-packed tiles on an unbounded plane.
+    Sparse,
+}
+
+/// Default cache size for engines that memoise results.
+pub const DEFAULT_MEMORY_LIMIT: usize = 512 * 1024 * 1024;
+
+impl Backend {
+    /// Creates an empty engine. Bounded backends use the given dimensions and
+    /// topology; HashLife and Sparse always run on an unbounded plane.
+    pub fn create(
+        self,
+        width: u32,
+        height: u32,
+        topology: Topology,
+    ) -> Result<Box<dyn Engine>, String> {
+        match self {
+            Backend::Dense => {
+                let mut universe = Universe::new(width, height);
+                universe.set_topology(topology)?;
+                Ok(Box::new(universe))
+            }
+            Backend::Packed => match topology {
+                Topology::Torus | Topology::Plane => {
+                    Ok(Box::new(PackedUniverse::new(width, height, topology)))
+                }
+                _ => Err(format!(
+                    "the packed backend does not support the {} topology",
+                    topology
+                )),
+            },
+            Backend::HashLife => Ok(Box::new(HashLife::new(DEFAULT_MEMORY_LIMIT))),
+            Backend::Sparse => Ok(Box::new(SparseUniverse::new())),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "dense" => Ok(Backend::Dense),
+            "packed" => Ok(Backend::Packed),
+            "hashlife" => Ok(Backend::HashLife),
+            "sparse" => Ok(Backend::Sparse),
+            _ => Err(format!(
+                "unknown backend '{}', expected dense, packed, hashlife or sparse",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Dense => write!(f, "dense"),
+            Backend::Packed => write!(f, "packed"),
+            Backend::HashLife => write!(f, "hashlife"),
+            Backend::Sparse => write!(f, "sparse"),
+        }
+    }
+}
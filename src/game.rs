@@ -1,12 +1,113 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, path::Path};
 
 use wgpu::util::DeviceExt;
 use winit::{
-    event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
+    event::{
+        ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode,
+        WindowEvent,
+    },
     window::Window,
 };
 
-use crate::cell::Cell;
+use game_of_life::{
+    rule::{self, file::RuleFile},
+    Anchor, Backend, Engine, Geometry, Pattern, Rect, Rule,
+};
+
+use crate::{
+    camera::Camera,
+    cell::Cell,
+    clipboard::Clipboard,
+    config::Config,
+    history::{History, HISTORY_MEMORY_LIMIT},
+    hud::Hud,
+    rewind::Rewind,
+};
+
+const SAVE_PATH: &str = "pattern.rle";
+/// Zoom factor per line of mouse wheel scrolling.
+const WHEEL_ZOOM: f64 = 1.2;
+/// Fraction of cells brought to life by a random fill.
+const RANDOM_FILL_DENSITY: f32 = 0.25;
+/// Flags or-ed into the state buffer to highlight the selection and the paste
+/// preview. They must match the shader.
+const SELECTED_FLAG: u32 = 1 << 31;
+const PREVIEW_FLAG: u32 = 1 << 30;
+/// Recorded generations skipped by Page Up and Page Down.
+const REWIND_SCRUB_FRAMES: isize = 10;
+/// The largest step size, as a power of two, for backends that compute
+/// every generation in turn. HashLife jumps ahead and goes up to 2^63.
+const MAX_STEP_EXPONENT: u32 = 10;
+
+fn digit(key: VirtualKeyCode) -> Option<u64> {
+    use VirtualKeyCode::*;
+    match key {
+        Key0 | Numpad0 => Some(0),
+        Key1 | Numpad1 => Some(1),
+        Key2 | Numpad2 => Some(2),
+        Key3 | Numpad3 => Some(3),
+        Key4 | Numpad4 => Some(4),
+        Key5 | Numpad5 => Some(5),
+        Key6 | Numpad6 => Some(6),
+        Key7 | Numpad7 => Some(7),
+        Key8 | Numpad8 => Some(8),
+        Key9 | Numpad9 => Some(9),
+        _ => None,
+    }
+}
+
+/// The cells on a Bresenham line from `from` to `to`, excluding `from`.
+fn line(from: (i64, i64), to: (i64, i64)) -> impl Iterator<Item = (i64, i64)> {
+    let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let (step_x, step_y) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let (mut x, mut y, mut error) = (from.0, from.1, dx + dy);
+    std::iter::from_fn(move || {
+        if (x, y) == to {
+            return None;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+        Some((x, y))
+    })
+}
+
+/// The shader's palette uniform, with the given colours set and every other
+/// state left to its default colour.
+fn palette_data(colours: &[(u32, [u8; 3])]) -> Vec<[f32; 4]> {
+    let mut palette = vec![[0.0; 4]; rule::tree::MAX_STATES as usize];
+    for &(state, [r, g, b]) in colours {
+        if let Some(entry) = palette.get_mut(state as usize) {
+            *entry = [r, g, b, 255].map(|c| c as f32 / 255.0);
+        }
+    }
+    palette
+}
+
+/// Model matrices for the cells of `view`, `size` pixels wide, relative to
+/// its top-left cell.
+fn model_matrices(view: Rect, size: f32, geometry: Geometry) -> Vec<[[f32; 4]; 4]> {
+    (0..view.height as i64)
+        .flat_map(|y| {
+            (0..view.width as i64).map(move |x| {
+                let (cell_x, cell_y) = (view.x + x, view.y + y);
+                let x = x as f64 + geometry.row_offset(cell_y);
+                Cell {
+                    position: glam::vec2(x as f32 * size, y as f32 * size),
+                    flipped: geometry == Geometry::Triangular
+                        && geometry.parity(cell_x, cell_y) == 1,
+                }
+            })
+        })
+        .map(|cell| cell.model_matrix(size))
+        .collect()
+}
 
 pub struct Game {
     surface: wgpu::Surface,
@@ -16,35 +117,60 @@ pub struct Game {
     size: winit::dpi::PhysicalSize<u32>,
 
     cell_size: u32,
-    num_cells_x: u32,
-    num_cells_y: u32,
-    dx: Vec<i32>,
-    dy: Vec<i32>,
+    universe: Box<dyn Engine>,
+    history: History,
+    rewind: Rewind,
+    state_data: Vec<u32>,
+    resize_anchor: Anchor,
+    /// Whether the grid is resized to fill the window.
+    fit_window: bool,
+
+    camera: Camera,
+    /// Cells covered by the instance grid, and the zoom it was built for.
+    view: Rect,
+    view_zoom: f64,
 
     pv_mat: glam::Mat4,
     pv_mat_buffer: wgpu::Buffer,
-    pv_mat_bind_group: wgpu::BindGroup,
+    /// State colours from the current rule file, see `palette_data`.
+    palette_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
 
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
+    index_count: u32,
     state_buffer: wgpu::Buffer,
     model_mats_buffer: wgpu::Buffer,
 
-    current_state_data: Vec<u32>,
-    next_state_data: Vec<u32>,
-
     render_pipeline: wgpu::RenderPipeline,
+    hud: Hud,
 
     mouse_pos: glam::Vec2,
-    mouse_clicked: bool,
+    /// State being painted and the last cell painted, while a button is held.
+    stroke: Option<(u32, (i64, i64))>,
+    panning: bool,
+    /// Corner the selection is being dragged from.
+    selecting: Option<(i64, i64)>,
+    selection: Option<Rect>,
+    /// Pattern following the cursor until it is stamped or cancelled.
+    paste: Option<Pattern>,
+    clipboard: Clipboard,
+    modifiers: ModifiersState,
     updating: bool,
+    /// Generation at which a bounded run pauses.
+    run_until: Option<u64>,
+    /// Number typed before a run command.
+    count: Option<u64>,
+    rule_preset: usize,
 
     time_between_generations: f32,
+    step_exponent: u32,
+    max_step_exponent: u32,
     last_update_time: std::time::Instant,
 }
 
 impl Game {
-    pub async fn new(window: &Window) -> Self {
+    pub async fn new(window: &Window, settings: &Config) -> Result<Self, String> {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::Backends::all());
@@ -76,76 +202,115 @@ impl Game {
             format: surface.get_preferred_format(&adapter).unwrap(),
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode: settings.present_mode,
         };
         surface.configure(&device, &config);
 
-        let num_cells_x = 80;
-        let (num_cells_y, cell_size) = Self::calculate_cells(num_cells_x, &size);
-        let cells = (0..num_cells_y)
-            .into_iter()
-            .flat_map(|y| {
-                (0..num_cells_x).into_iter().map(move |x| Cell {
-                    position: glam::vec2((x * cell_size) as f32, (y * cell_size) as f32),
-                    state: false,
-                })
-            })
-            .collect::<Vec<_>>();
-
-        let model_matricies_data = cells
-            .iter()
-            .map(|cell| cell.model_matrix(cell_size as f32))
-            .collect::<Vec<_>>();
+        let cell_size = settings.cell_size;
+        let (width, height) = if settings.fit_window {
+            let (width, height) = (size.width / cell_size, size.height / cell_size);
+            settings.topology.fit(width.max(1), height.max(1))
+        } else {
+            (settings.width, settings.height)
+        };
+        // Hexagons and triangles need an even number of rows or columns to
+        // wrap around.
+        let (num_cells_x, num_cells_y) = settings.geometry.fit(settings.topology, width, height);
+        let camera = Camera::new(cell_size as f64, settings.geometry);
+        let view = camera.view_rect(size.width, size.height);
         let model_mats_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(&model_matricies_data),
+            contents: bytemuck::cast_slice(&model_matrices(
+                view,
+                camera.zoom as f32,
+                settings.geometry,
+            )),
             usage: wgpu::BufferUsages::VERTEX,
         });
-        let state_data = cells.iter().map(|_| 0).collect::<Vec<_>>();
+        let mut universe = settings
+            .backend
+            .create(num_cells_x, num_cells_y, settings.topology)?;
+        universe.set_memory_limit(settings.memory_limit);
+        universe.set_geometry(settings.geometry)?;
+        if let Some(path) = &settings.pattern {
+            match Pattern::load(path) {
+                Ok(pattern) => {
+                    if let Some(rule) = pattern.rule.as_deref().and_then(|r| r.parse().ok()) {
+                        if let Err(e) = universe.set_rule(rule) {
+                            log::error!("{}: {}", path.display(), e);
+                        }
+                    }
+                    let (x, y) = settings.geometry.align(
+                        (num_cells_x as i64 - pattern.width as i64) / 2,
+                        (num_cells_y as i64 - pattern.height as i64) / 2,
+                    );
+                    universe.place(&pattern, x, y);
+                }
+                Err(e) => log::error!("Failed to load {}: {}", path.display(), e),
+            }
+        }
+        if let Some(rule) = &settings.rule {
+            universe.set_rule(rule.clone())?;
+        }
+        let mut colours = Vec::new();
+        if let Some(path) = &settings.rule_file {
+            let file = RuleFile::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            universe.set_rule(file.rule)?;
+            colours = file.colours;
+        }
+        let mut state_data = vec![0; (view.width * view.height) as usize];
+        universe.read_region(view, &mut state_data);
         let state_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             contents: bytemuck::cast_slice(&state_data),
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
 
-        let pv_mat = glam::Mat4::orthographic_rh(
-            0.0,
-            size.width as f32,
-            size.height as f32,
-            0.0,
-            0.0,
-            100.0,
-        );
+        let pv_mat = camera.view_matrix(size.width, size.height);
         let pv_mat_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             contents: bytemuck::cast_slice(&(pv_mat.to_cols_array_2d())),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
-        let pv_mat_bind_group_layout =
+        let palette_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&palette_data(&colours)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let uniform_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            count: None,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        };
+        let uniform_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: None,
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    count: None,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                }],
+                entries: &[
+                    uniform_entry(0, wgpu::ShaderStages::VERTEX),
+                    uniform_entry(1, wgpu::ShaderStages::FRAGMENT),
+                ],
             });
-        let pv_mat_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: None,
-            layout: &pv_mat_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: pv_mat_buffer.as_entire_binding(),
-            }],
+            layout: &uniform_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: pv_mat_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: palette_buffer.as_entire_binding(),
+                },
+            ],
         });
 
-        let vertex_data: Vec<f32> = vec![0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0];
-        let index_data: Vec<u16> = vec![0, 1, 2, 2, 1, 3];
+        let (vertex_data, index_data) = Cell::mesh(settings.geometry);
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             contents: bytemuck::cast_slice(&vertex_data),
@@ -164,7 +329,7 @@ impl Game {
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: None,
-                bind_group_layouts: &[&pv_mat_bind_group_layout],
+                bind_group_layouts: &[&uniform_bind_group_layout],
                 push_constant_ranges: &[],
             });
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -189,62 +354,153 @@ impl Game {
             multiview: None,
         });
 
-        Self {
+        let hud = Hud::new(&device, config.format);
+
+        Ok(Self {
             surface,
             device,
             queue,
             config,
             size,
 
-            num_cells_x,
-            num_cells_y,
             cell_size,
-            dx: vec![-1, -1, -1, 0, 0, 1, 1, 1],
-            dy: vec![-1, 0, 1, -1, 1, -1, 0, 1],
+            universe,
+            history: History::new(HISTORY_MEMORY_LIMIT),
+            rewind: Rewind::new(settings.rewind_memory),
+            state_data,
+            resize_anchor: settings.resize_anchor,
+            fit_window: settings.fit_window,
+
+            view_zoom: camera.zoom,
+            camera,
+            view,
 
             pv_mat,
             pv_mat_buffer,
-            pv_mat_bind_group,
+            palette_buffer,
+            uniform_bind_group,
 
             vertex_buffer,
             index_buffer,
+            index_count: index_data.len() as u32,
             state_buffer,
             model_mats_buffer,
 
             render_pipeline,
-
-            next_state_data: vec![0; state_data.len()],
-            current_state_data: state_data,
+            hud,
 
             mouse_pos: glam::vec2(0.0, 0.0),
-            mouse_clicked: false,
+            stroke: None,
+            panning: false,
+            selecting: None,
+            selection: None,
+            paste: None,
+            clipboard: Clipboard::new(),
+            modifiers: ModifiersState::empty(),
             updating: false,
-            time_between_generations: 0.2,
+            run_until: None,
+            count: None,
+            rule_preset: 0,
+            time_between_generations: settings.interval,
+            step_exponent: 0,
+            max_step_exponent: match settings.backend {
+                Backend::HashLife => 63,
+                _ => MAX_STEP_EXPONENT,
+            },
             last_update_time: std::time::Instant::now(),
-        }
+        })
     }
 
+    /// Handles window input. With the mouse, a left drag paints (or erases
+    /// if it starts on a live cell), a right drag erases, a middle drag pans,
+    /// the wheel zooms and shift-left drag selects.
     pub fn input(&mut self, event: &WindowEvent) {
         match event {
-            WindowEvent::Resized(physical_size) => {
-                self.size = *physical_size;
-                self.resize();
-            }
+            WindowEvent::Resized(physical_size) => self.set_size(*physical_size),
             WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                self.size = **new_inner_size;
-                self.resize();
+                self.set_size(**new_inner_size)
             }
 
             WindowEvent::CursorMoved { position, .. } => {
                 // println!("Mouse move");
-                self.mouse_pos = glam::vec2(position.x as f32, position.y as f32);
+                let mouse_pos = glam::vec2(position.x as f32, position.y as f32);
+                if self.panning {
+                    self.camera.pan(mouse_pos - self.mouse_pos);
+                    self.camera_moved();
+                }
+                self.mouse_pos = mouse_pos;
+                if let Some((state, last)) = self.stroke {
+                    let cell = self.mouse_cell();
+                    for (x, y) in line(last, cell) {
+                        self.universe.set(x, y, state);
+                    }
+                    self.stroke = Some((state, cell));
+                }
+                if let Some(anchor) = self.selecting {
+                    self.selection = Rect::bounding([anchor, self.mouse_cell()]);
+                }
                 self.update();
             }
             WindowEvent::MouseInput {
-                state: ElementState::Released,
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if self.paste.is_some() => {
+                self.history.record(self.universe.as_ref());
+                let (x, y) = self.paste_origin();
+                self.universe.place(self.paste.as_ref().unwrap(), x, y);
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
                 button: MouseButton::Left,
                 ..
-            } => self.mouse_clicked = true,
+            } if self.modifiers.shift() => {
+                let cell = self.mouse_cell();
+                self.selecting = Some(cell);
+                self.selection = Rect::bounding([cell]);
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: button @ (MouseButton::Left | MouseButton::Right),
+                ..
+            } => {
+                // A left stroke erases if it starts on a live cell.
+                self.history.record(self.universe.as_ref());
+                let (x, y) = self.mouse_cell();
+                let state = match button {
+                    MouseButton::Left if self.universe.get(x, y) == 0 => 1,
+                    _ => 0,
+                };
+                self.universe.set(x, y, state);
+                self.stroke = Some((state, (x, y)));
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left | MouseButton::Right,
+                ..
+            } => {
+                self.stroke = None;
+                self.selecting = None;
+            }
+            // The right button erases, so only the middle button pans.
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Middle,
+                ..
+            } => self.panning = *state == ElementState::Pressed,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y as f64,
+                    MouseScrollDelta::PixelDelta(position) => position.y / 40.0,
+                };
+                self.camera.zoom_at(self.mouse_pos, WHEEL_ZOOM.powf(lines));
+                self.camera_moved();
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            WindowEvent::DroppedFile(path) if path.extension().is_some_and(|e| e == "rule") => {
+                self.load_rule_file(path)
+            }
+            WindowEvent::DroppedFile(path) => self.load_pattern(path),
 
             WindowEvent::KeyboardInput {
                 input:
@@ -254,20 +510,309 @@ impl Game {
                         ..
                     },
                 ..
-            } => self.updating = !self.updating,
+            } => {
+                if !self.updating {
+                    self.history.record(self.universe.as_ref());
+                }
+                self.updating = !self.updating;
+                self.run_until = None;
+            }
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
                         state: ElementState::Pressed,
-                        virtual_keycode: Some(VirtualKeyCode::Plus),
+                        virtual_keycode: Some(VirtualKeyCode::S),
+                        ..
+                    },
+                ..
+            } if self.modifiers.ctrl() => self.save_pattern(SAVE_PATH),
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key @ (VirtualKeyCode::C | VirtualKeyCode::X)),
+                        ..
+                    },
+                ..
+            } if self.modifiers.ctrl() => match self.selection {
+                Some(selection) => {
+                    self.clipboard.copy(self.universe.copy_region(selection));
+                    if *key == VirtualKeyCode::X {
+                        self.history.record(self.universe.as_ref());
+                        self.universe.clear_region(selection);
+                    }
+                }
+                None => log::info!("Nothing selected"),
+            },
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode:
+                            Some(
+                                key @ (VirtualKeyCode::Period
+                                | VirtualKeyCode::Comma
+                                | VirtualKeyCode::X
+                                | VirtualKeyCode::Y),
+                            ),
+                        ..
+                    },
+                ..
+            } => {
+                let transform = match key {
+                    VirtualKeyCode::Period => Pattern::rotate_clockwise,
+                    VirtualKeyCode::Comma => Pattern::rotate_anticlockwise,
+                    VirtualKeyCode::X => Pattern::flip_horizontal,
+                    _ => Pattern::flip_vertical,
+                };
+                self.transform(transform);
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::V),
+                        ..
+                    },
+                ..
+            } if self.modifiers.ctrl() => {
+                self.paste = self.clipboard.paste();
+                if self.paste.is_none() {
+                    log::info!("Nothing to paste");
+                }
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Z),
+                        ..
+                    },
+                ..
+            } if self.modifiers.ctrl() => {
+                self.updating = false;
+                let rule = self.universe.rule().clone();
+                let done = if self.modifiers.shift() {
+                    self.history.redo(self.universe.as_mut())
+                } else {
+                    self.history.undo(self.universe.as_mut())
+                };
+                if self.universe.rule() != &rule {
+                    self.set_palette(&[]);
+                    log::info!("Rule: {}", self.universe.rule());
+                }
+                if !done {
+                    log::info!(
+                        "Nothing to {}",
+                        if self.modifiers.shift() {
+                            "redo"
+                        } else {
+                            "undo"
+                        }
+                    );
+                }
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode:
+                            Some(
+                                key @ (VirtualKeyCode::Left
+                                | VirtualKeyCode::Right
+                                | VirtualKeyCode::PageUp
+                                | VirtualKeyCode::PageDown),
+                            ),
                         ..
                     },
                 ..
             } => {
-                if self.time_between_generations - 0.03 >= 0.0 {
-                    self.time_between_generations -= 0.02
+                let offset = match key {
+                    VirtualKeyCode::Left => -1,
+                    VirtualKeyCode::Right => 1,
+                    VirtualKeyCode::PageUp => -REWIND_SCRUB_FRAMES,
+                    _ => REWIND_SCRUB_FRAMES,
+                };
+                self.updating = false;
+                match self.rewind.seek(self.universe.as_mut(), offset) {
+                    Some(generation) => log::info!("Generation {}", generation),
+                    None => log::info!("No more recorded generations"),
                 }
             }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } if digit(*key).is_some_and(|d| d > 0 || self.count.is_some()) => {
+                let count = self.count.unwrap_or(0);
+                self.count = Some(
+                    count
+                        .saturating_mul(10)
+                        .saturating_add(digit(*key).unwrap()),
+                );
+                log::info!("Count: {}", self.count.unwrap());
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                ..
+            } => {
+                self.count = None;
+                self.paste = None;
+                self.selection = None;
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Tab),
+                        ..
+                    },
+                ..
+            } => {
+                self.updating = false;
+                self.run_until = None;
+                self.history.record(self.universe.as_ref());
+                self.advance(1 << self.step_exponent);
+                log::info!("Generation {}", self.universe.generation());
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key @ (VirtualKeyCode::Return | VirtualKeyCode::G)),
+                        ..
+                    },
+                ..
+            } => match self.count.take() {
+                // Enter runs N more generations, G runs up to generation N.
+                Some(count) => {
+                    let generation = self.universe.generation();
+                    let target = match key {
+                        VirtualKeyCode::Return => generation.saturating_add(count),
+                        _ => count,
+                    };
+                    if target > generation {
+                        self.history.record(self.universe.as_ref());
+                        self.run_until = Some(target);
+                        self.updating = true;
+                        log::info!("Running until generation {}", target);
+                    } else {
+                        log::info!("Already at generation {}", generation);
+                    }
+                }
+                None => log::info!("Type a number first"),
+            },
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Key0),
+                        ..
+                    },
+                ..
+            } => {
+                self.updating = false;
+                if self.rewind.rewind_to_start(self.universe.as_mut()) {
+                    log::info!("Generation 0");
+                } else {
+                    log::info!("Generation 0 was not recorded");
+                }
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Delete | VirtualKeyCode::Back),
+                        ..
+                    },
+                ..
+            } => {
+                self.history.record(self.universe.as_ref());
+                match self.selection {
+                    Some(selection) => self.universe.clear_region(selection),
+                    None => self.universe.clear(),
+                }
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::N),
+                        ..
+                    },
+                ..
+            } => self.random_fill(),
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::A),
+                        ..
+                    },
+                ..
+            } => {
+                self.resize_anchor = match self.resize_anchor {
+                    Anchor::TopLeft => Anchor::Centre,
+                    Anchor::Centre => Anchor::TopLeft,
+                };
+                log::info!("Resize anchor: {:?}", self.resize_anchor);
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F),
+                        ..
+                    },
+                ..
+            } => match self.universe.bounding_box() {
+                Some(rect) => {
+                    self.camera.fit(rect, self.size.width, self.size.height);
+                    self.camera_moved();
+                }
+                None => log::info!("Nothing to fit"),
+            },
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::H),
+                        ..
+                    },
+                ..
+            } => self.hud.visible = !self.hud.visible,
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Home),
+                        ..
+                    },
+                ..
+            } => {
+                self.camera = Camera::new(self.cell_size as f64, self.camera.geometry);
+                self.camera_moved();
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Plus),
+                        ..
+                    },
+                ..
+            } if self.time_between_generations - 0.03 >= 0.0 => {
+                self.time_between_generations -= 0.02
+            }
 
             WindowEvent::KeyboardInput {
                 input:
@@ -278,59 +823,83 @@ impl Game {
                     },
                 ..
             } => self.time_between_generations += 0.02,
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::RBracket),
+                        ..
+                    },
+                ..
+            } if self.step_exponent < self.max_step_exponent => {
+                self.step_exponent += 1;
+                log::info!("Step size: 2^{}", self.step_exponent);
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::LBracket),
+                        ..
+                    },
+                ..
+            } if self.step_exponent > 0 => {
+                self.step_exponent -= 1;
+                log::info!("Step size: 2^{}", self.step_exponent);
+            }
+
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::R),
+                        ..
+                    },
+                ..
+            } => {
+                self.rule_preset = (self.rule_preset + 1) % rule::PRESETS.len();
+                let (name, rule) = rule::PRESETS[self.rule_preset];
+                match self.universe.set_rule(rule.parse::<Rule>().unwrap()) {
+                    Ok(()) => {
+                        self.set_palette(&[]);
+                        log::info!("Rule: {} ({})", name, rule);
+                    }
+                    Err(e) => log::error!("Can't switch to {}: {}", name, e),
+                }
+            }
 
             _ => {}
         }
     }
 
     pub fn update(&mut self) {
-        let cell_x = self.mouse_pos.x as u32 / self.cell_size;
-        let cell_y = self.mouse_pos.y as u32 / self.cell_size;
-        let cell_index = self.position_to_index(cell_x as i32, cell_y as i32);
-        if self.mouse_clicked {
-            self.current_state_data[cell_index] = 1 - self.current_state_data[cell_index].min(1);
-            self.mouse_clicked = false;
-        }
         if self.updating {
             let now = std::time::Instant::now();
             let elapsed = now.duration_since(self.last_update_time).as_secs_f32();
             if elapsed >= self.time_between_generations || self.time_between_generations <= 0.002 {
-                (0..self.num_cells_y).into_iter().for_each(|y| {
-                    (0..self.num_cells_x).into_iter().for_each(|x| {
-                        let mut neighbours = 0;
-                        (0..self.dx.len()).into_iter().for_each(|index| {
-                            neighbours += self.current_state_data[self.position_to_index(
-                                x as i32 + self.dx[index],
-                                y as i32 + self.dy[index],
-                            )]
-                            .min(1);
-                        });
-                        let index = self.position_to_index(x as i32, y as i32);
-                        if self.current_state_data[index] > 0 {
-                            if neighbours == 2 || neighbours == 3 {
-                                self.next_state_data[index] = 1;
-                            } else {
-                                self.next_state_data[index] = 0;
-                            }
-                        } else {
-                            if neighbours == 3 {
-                                self.next_state_data[index] = 1;
-                            } else {
-                                self.next_state_data[index] = 0;
-                            }
-                        }
-                    })
-                });
-
-                std::mem::swap(&mut self.current_state_data, &mut self.next_state_data);
+                let mut step = 1 << self.step_exponent;
+                if let Some(target) = self.run_until {
+                    step = step.min(target.saturating_sub(self.universe.generation()));
+                }
+                self.advance(step);
                 self.last_update_time = now;
+                if self
+                    .run_until
+                    .is_some_and(|target| self.universe.generation() >= target)
+                {
+                    self.updating = false;
+                    self.run_until = None;
+                    log::info!("Paused at generation {}", self.universe.generation());
+                }
             }
         }
 
+        self.universe.read_region(self.view, &mut self.state_data);
+        self.draw_overlays();
         self.queue.write_buffer(
             &self.state_buffer,
             0,
-            bytemuck::cast_slice(&self.current_state_data),
+            bytemuck::cast_slice(&self.state_data),
         );
     }
 
@@ -362,15 +931,26 @@ impl Game {
                 depth_stencil_attachment: None,
             });
             render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.pv_mat_bind_group, &[]);
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             render_pass.set_vertex_buffer(1, self.model_mats_buffer.slice(..));
             render_pass.set_vertex_buffer(2, self.state_buffer.slice(..));
             render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..6, 0, 0..(self.num_cells_x * self.num_cells_y));
+            render_pass.draw_indexed(
+                0..self.index_count,
+                0,
+                0..(self.view.width * self.view.height) as u32,
+            );
+        }
+        self.hud.tick(self.universe.generation());
+        if self.hud.visible {
+            let text = self.hud_text();
+            self.hud
+                .draw(&self.device, &mut encoder, &view, self.size, &text);
         }
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
+        self.hud.recall();
 
         Ok(())
     }
@@ -379,56 +959,61 @@ impl Game {
         self.config.width = self.size.width;
         self.config.height = self.size.height;
         self.surface.configure(&self.device, &self.config);
+        self.camera_moved();
+    }
 
-        self.num_cells_x = (self.config.width as f32 / self.cell_size as f32).ceil() as u32;
-        self.num_cells_y = (self.config.height as f32 / self.cell_size as f32).ceil() as u32;
-        self.recalculate_model_matricies();
-        self.resize_state_buffer();
-        self.recalculate_proj_matrix();
+    /// Adopts a new window size. With `fit_window` the grid is resized to
+    /// fill the window, keeping its cells at `resize_anchor`; otherwise the
+    /// grid keeps its size and the view keeps that point in place.
+    fn set_size(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        if self.fit_window {
+            let (width, height) = (size.width / self.cell_size, size.height / self.cell_size);
+            if width > 0 && height > 0 {
+                self.universe.resize(width, height, self.resize_anchor);
+            }
+        } else if self.resize_anchor == Anchor::Centre {
+            let old = glam::vec2(self.size.width as f32, self.size.height as f32);
+            let new = glam::vec2(size.width as f32, size.height as f32);
+            self.camera.pan((new - old) / 2.0);
+        }
+        self.size = size;
+        self.resize();
     }
 
-    fn calculate_cells(num_cells_x: u32, size: &winit::dpi::PhysicalSize<u32>) -> (u32, u32) {
-        let cell_size = size.width as f32 / num_cells_x as f32;
-        let num_cells_y = size.height as f32 / cell_size;
-        (num_cells_y.ceil() as u32, cell_size.ceil() as u32)
+    /// Rebuilds whatever the camera's new position and zoom invalidate.
+    fn camera_moved(&mut self) {
+        let view = self.camera.view_rect(self.size.width, self.size.height);
+        let resized = (view.width, view.height) != (self.view.width, self.view.height);
+        // Which cells are shifted or flipped depends on where the view starts.
+        let geometry = self.camera.geometry;
+        let reparity = geometry.parity(view.x, view.y) != geometry.parity(self.view.x, self.view.y);
+        self.view = view;
+        if resized || reparity || self.view_zoom != self.camera.zoom {
+            self.view_zoom = self.camera.zoom;
+            self.recalculate_model_matricies();
+        }
+        if resized {
+            self.resize_state_buffer();
+        }
+        self.recalculate_proj_matrix();
     }
 
     fn resize_state_buffer(&mut self) {
         self.state_buffer.destroy();
-        let state_data: Vec<u32> = vec![0; (self.num_cells_x * self.num_cells_y) as usize];
+        self.state_data = vec![0; (self.view.width * self.view.height) as usize];
+        self.universe.read_region(self.view, &mut self.state_data);
         self.state_buffer = self
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: None,
-                contents: bytemuck::cast_slice(&state_data),
+                contents: bytemuck::cast_slice(&self.state_data),
                 usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             });
-
-        self.current_state_data
-            .resize((self.num_cells_x * self.num_cells_y) as usize, 0);
-        self.next_state_data
-            .resize((self.num_cells_x * self.num_cells_y) as usize, 0);
     }
 
     fn recalculate_model_matricies(&mut self) {
-        let cell_size = self.cell_size;
-        let cells = (0..self.num_cells_y)
-            .into_iter()
-            .flat_map(|y| {
-                (0..self.num_cells_x).into_iter().map(move |x| {
-                    // println!("{}, {}", x * cell_size, y * cell_size);
-                    Cell {
-                        position: glam::vec2((x * cell_size) as f32, (y * cell_size) as f32),
-                        state: false,
-                    }
-                })
-            })
-            .collect::<Vec<_>>();
-
-        let model_matricies_data = cells
-            .iter()
-            .map(|cell| cell.model_matrix(self.cell_size as f32))
-            .collect::<Vec<_>>();
+        let model_matricies_data =
+            model_matrices(self.view, self.camera.zoom as f32, self.camera.geometry);
         self.model_mats_buffer =
             self.device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -439,14 +1024,7 @@ impl Game {
     }
 
     fn recalculate_proj_matrix(&mut self) {
-        self.pv_mat = glam::Mat4::orthographic_rh(
-            0.0,
-            self.size.width as f32,
-            self.size.height as f32,
-            0.0,
-            0.0,
-            100.0,
-        );
+        self.pv_mat = self.camera.view_matrix(self.size.width, self.size.height);
         self.queue.write_buffer(
             &self.pv_mat_buffer,
             0,
@@ -454,17 +1032,161 @@ impl Game {
         );
     }
 
-    fn position_to_index(&self, mut x: i32, mut y: i32) -> usize {
-        if x < 0 {
-            x = (self.num_cells_x - 1) as i32;
-        } else if x >= self.num_cells_x as i32 {
-            x = 0;
+    fn mouse_cell(&self) -> (i64, i64) {
+        self.camera.screen_to_cell(self.mouse_pos)
+    }
+
+    /// Where the paste preview's top-left corner goes: near the mouse, on a
+    /// cell where the pattern keeps its shape.
+    fn paste_origin(&self) -> (i64, i64) {
+        let (x, y) = self.mouse_cell();
+        self.camera.geometry.align(x, y)
+    }
+
+    /// Applies a rotation or flip to the paste preview if there is one,
+    /// otherwise to the selected cells, turning the selection about its
+    /// centre.
+    fn transform(&mut self, transform: fn(&Pattern) -> Pattern) {
+        if let Some(paste) = &self.paste {
+            self.paste = Some(transform(paste));
+            return;
+        }
+        let Some(selection) = self.selection else {
+            log::info!("Nothing selected");
+            return;
+        };
+
+        self.history.record(self.universe.as_ref());
+        let pattern = transform(&self.universe.copy_region(selection));
+        let x = selection.x + (selection.width as i64 - pattern.width as i64) / 2;
+        let y = selection.y + (selection.height as i64 - pattern.height as i64) / 2;
+        self.universe.clear_region(selection);
+        self.universe.place(&pattern, x, y);
+        self.selection = Some(Rect {
+            x,
+            y,
+            width: pattern.width as u64,
+            height: pattern.height as u64,
+        });
+    }
+
+    /// Flags the selected cells and the paste preview in the state buffer.
+    fn draw_overlays(&mut self) {
+        let view = self.view;
+        let index =
+            |x: i64, y: i64| ((y - view.y) as u64 * view.width + (x - view.x) as u64) as usize;
+        if let Some(selection) = self.selection {
+            let x0 = selection.x.max(view.x);
+            let y0 = selection.y.max(view.y);
+            let x1 = (selection.x + selection.width as i64).min(view.x + view.width as i64);
+            let y1 = (selection.y + selection.height as i64).min(view.y + view.height as i64);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    self.state_data[index(x, y)] |= SELECTED_FLAG;
+                }
+            }
+        }
+        if let Some(paste) = &self.paste {
+            let (origin_x, origin_y) = self.paste_origin();
+            for &(x, y, _) in &paste.cells {
+                let (x, y) = (origin_x + x as i64, origin_y + y as i64);
+                if view.contains(x, y) {
+                    self.state_data[index(x, y)] |= PREVIEW_FLAG;
+                }
+            }
+        }
+    }
+
+    fn hud_text(&self) -> String {
+        let state = match (self.updating, self.run_until) {
+            (false, _) => "Paused".to_string(),
+            (true, None) => "Running".to_string(),
+            (true, Some(target)) => format!("Running until generation {}", target),
+        };
+        format!(
+            "Generation {}\nPopulation {}\n{:.1} gen/s (step 2^{}, interval {:.2} s)\n\
+             Frame {:.1} ms\nRule {}\nTopology {}\n{}",
+            self.universe.generation(),
+            self.universe.population(),
+            self.hud.generations_per_second(),
+            self.step_exponent,
+            self.time_between_generations,
+            self.hud.frame_time().as_secs_f64() * 1000.0,
+            self.universe.rule(),
+            self.universe
+                .topology()
+                .map_or("unbounded".to_string(), |t| t.to_string()),
+            state,
+        )
+    }
+
+    /// Steps the universe, recording the current generation for rewinding.
+    fn advance(&mut self, generations: u64) {
+        self.rewind.record(self.universe.as_ref());
+        self.universe.step_n(generations);
+    }
+
+    /// Randomly fills the visible cells.
+    fn random_fill(&mut self) {
+        self.history.record(self.universe.as_ref());
+        let view = self.view;
+        for y in view.y..view.y + view.height as i64 {
+            for x in view.x..view.x + view.width as i64 {
+                let alive = fastrand::f32() < RANDOM_FILL_DENSITY;
+                self.universe.set(x, y, alive as u32);
+            }
+        }
+    }
+
+    fn load_rule_file(&mut self, path: &Path) {
+        let result = RuleFile::load(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                self.universe.set_rule(file.rule)?;
+                Ok(file.colours)
+            });
+        match result {
+            Ok(colours) => {
+                self.set_palette(&colours);
+                log::info!("Rule: {}", self.universe.rule());
+            }
+            Err(e) => log::error!("Failed to load {}: {}", path.display(), e),
+        }
+    }
+
+    fn set_palette(&mut self, colours: &[(u32, [u8; 3])]) {
+        self.queue.write_buffer(
+            &self.palette_buffer,
+            0,
+            bytemuck::cast_slice(&palette_data(colours)),
+        );
+    }
+
+    fn load_pattern(&mut self, path: &Path) {
+        match Pattern::load(path) {
+            Ok(pattern) => {
+                self.history.record(self.universe.as_ref());
+                if let Some(rule) = pattern.rule.as_deref().and_then(|r| r.parse::<Rule>().ok()) {
+                    if let Err(e) = self.universe.set_rule(rule) {
+                        log::error!("{}: {}", path.display(), e);
+                    }
+                }
+                let (x, y) = self.mouse_cell();
+                self.universe.place(&pattern, x, y);
+                log::info!("Loaded {} at ({}, {})", path.display(), x, y);
+            }
+            Err(e) => log::error!("Failed to load {}: {}", path.display(), e),
         }
-        if y < 0 {
-            y = (self.num_cells_y - 1) as i32;
-        } else if y >= self.num_cells_y as i32 {
-            y = 0;
+    }
+
+    fn save_pattern(&self, path: &str) {
+        match self
+            .universe
+            .extract()
+            .and_then(|pattern| pattern.save(path))
+        {
+            Ok(()) => log::info!("Saved pattern to {}", path),
+            Err(e) => log::error!("Failed to save {}: {}", path, e),
         }
-        (y * self.num_cells_x as i32 + x) as usize
     }
 }
@@ -0,0 +1,224 @@
+use std::{fmt, str::FromStr};
+
+use crate::universe::Topology;
+
+/// The shape of the cells and which of them are neighbours. Cells are always
+/// addressed by column and row; hexagonal and triangular grids differ in how
+/// those are laid out and connected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Geometry {
+    /// Squares with the eight neighbours of the Moore neighbourhood.
+    #[default]
+    Square,
+    /// Pointy-topped hexagons with six neighbours. Odd rows are shifted half
+    /// a cell to the right.
+    Hexagonal,
+    /// Triangles with the three neighbours they share an edge with. A cell
+    /// points up when its column and row add up to an even number.
+    Triangular,
+}
+
+const MOORE: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const HEX_EVEN_ROW: [(i32, i32); 6] = [(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+const HEX_ODD_ROW: [(i32, i32); 6] = [(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+const TRIANGLE_UP: [(i32, i32); 3] = [(-1, 0), (1, 0), (0, 1)];
+const TRIANGLE_DOWN: [(i32, i32); 3] = [(0, -1), (-1, 0), (1, 0)];
+
+impl Geometry {
+    /// Which of the two kinds of cell the geometry has is at `(x, y)`: odd
+    /// hexagon rows and downward triangles are 1, everything else 0.
+    pub fn parity(self, x: i64, y: i64) -> usize {
+        match self {
+            Geometry::Square => 0,
+            Geometry::Hexagonal => y.rem_euclid(2) as usize,
+            Geometry::Triangular => (x + y).rem_euclid(2) as usize,
+        }
+    }
+
+    /// Offsets of the neighbours of the cell at `(x, y)`. On a wrapping grid
+    /// they only line up across the edges when [`Geometry::fits`] holds.
+    pub fn neighbours(self, x: i64, y: i64) -> &'static [(i32, i32)] {
+        match (self, self.parity(x, y)) {
+            (Geometry::Square, _) => &MOORE,
+            (Geometry::Hexagonal, 0) => &HEX_EVEN_ROW,
+            (Geometry::Hexagonal, _) => &HEX_ODD_ROW,
+            (Geometry::Triangular, 0) => &TRIANGLE_UP,
+            (Geometry::Triangular, _) => &TRIANGLE_DOWN,
+        }
+    }
+
+    /// Whether neighbours can line up across the edges of `topology`. Edges
+    /// joined with a twist mirror hexagons and triangles into the wrong
+    /// shape.
+    pub fn supports(self, topology: Topology) -> bool {
+        self == Geometry::Square
+            || matches!(
+                topology,
+                Topology::Torus | Topology::ShiftedTorus(_) | Topology::Plane
+            )
+    }
+
+    /// Whether neighbours line up across the edges of a `width` by `height`
+    /// grid with `topology`.
+    pub fn fits(self, topology: Topology, width: u32, height: u32) -> bool {
+        self.supports(topology) && self.fit(topology, width, height) == (width, height)
+    }
+
+    /// The smallest size of at least `width` by `height` on which neighbours
+    /// line up across the wrapped edges of `topology`: the cells leaving one
+    /// edge must be of the same kind as those they arrive next to.
+    pub fn fit(self, topology: Topology, width: u32, height: u32) -> (u32, u32) {
+        let shift = match topology {
+            Topology::Torus => 0,
+            Topology::ShiftedTorus(shift) => shift,
+            _ => return (width, height),
+        };
+        let odd = |n: i64| n.rem_euclid(2) as u32;
+        match self {
+            Geometry::Square => (width, height),
+            Geometry::Hexagonal => (width, height.saturating_add(odd(height as i64))),
+            Geometry::Triangular => (
+                width.saturating_add(odd(width as i64)),
+                height.saturating_add(odd(height as i64 - shift.rem_euclid(2))),
+            ),
+        }
+    }
+
+    /// The nearest cell at or before `(x, y)` of the same kind as `(0, 0)`, so
+    /// that a pattern placed there keeps its shape.
+    pub fn align(self, x: i64, y: i64) -> (i64, i64) {
+        match self {
+            Geometry::Square => (x, y),
+            Geometry::Hexagonal => (x, y - self.parity(x, y) as i64),
+            Geometry::Triangular => (x - self.parity(x, y) as i64, y),
+        }
+    }
+
+    /// How far row `y` is shifted to the right, in cells.
+    pub fn row_offset(self, y: i64) -> f64 {
+        match self {
+            Geometry::Hexagonal => self.parity(0, y) as f64 * 0.5,
+            _ => 0.0,
+        }
+    }
+
+    /// The cell containing a point, in units of one cell width with cell
+    /// `(x, y)` laid out from `(x + row_offset(y), y)`. Hexagons are one cell
+    /// wide and a quarter taller than a row, overlapping the rows above and
+    /// below by an eighth; triangles are two cells wide and one row tall.
+    pub fn cell_at(self, x: f64, y: f64) -> (i64, i64) {
+        let row = y.floor() as i64;
+        match self {
+            Geometry::Square => (x.floor() as i64, row),
+            Geometry::Hexagonal => {
+                // Each hexagon is the set of points closest to its centre.
+                let centre = |column: i64, row: i64| {
+                    let dx = column as f64 + self.row_offset(row) + 0.5 - x;
+                    let dy = row as f64 + 0.5 - y;
+                    dx * dx + dy * dy
+                };
+                (row - 1..=row + 1)
+                    .flat_map(|row| {
+                        let column = (x - self.row_offset(row)).floor() as i64;
+                        (column - 1..=column + 1).map(move |column| (column, row))
+                    })
+                    .min_by(|&a, &b| centre(a.0, a.1).total_cmp(&centre(b.0, b.1)))
+                    .unwrap_or((0, row))
+            }
+            Geometry::Triangular => {
+                let column = x.floor() as i64;
+                let (dx, dy) = (x - column as f64 - 0.5, y - row as f64);
+                // Distance from the apex, measured down an upward triangle.
+                let depth = if self.parity(column, row) == 0 {
+                    dy
+                } else {
+                    1.0 - dy
+                };
+                if dx.abs() <= depth {
+                    (column, row)
+                } else if dx < 0.0 {
+                    (column - 1, row)
+                } else {
+                    (column + 1, row)
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Geometry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "square" => Ok(Geometry::Square),
+            "hexagonal" | "hex" => Ok(Geometry::Hexagonal),
+            "triangular" | "tri" => Ok(Geometry::Triangular),
+            _ => Err(format!(
+                "unknown geometry '{}', expected square, hexagonal or triangular",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Geometry::Square => write!(f, "square"),
+            Geometry::Hexagonal => write!(f, "hexagonal"),
+            Geometry::Triangular => write!(f, "triangular"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_at_finds_the_cell_around_a_point() {
+        for geometry in [Geometry::Square, Geometry::Hexagonal, Geometry::Triangular] {
+            for y in -4..4 {
+                for x in -4..4 {
+                    // A point just inside the cell, near its centre.
+                    let centre_y = match (geometry, geometry.parity(x, y)) {
+                        (Geometry::Triangular, 0) => 0.6,
+                        (Geometry::Triangular, _) => 0.4,
+                        _ => 0.5,
+                    };
+                    let centre_x = x as f64 + geometry.row_offset(y) + 0.5;
+                    let centre = geometry.cell_at(centre_x, y as f64 + centre_y);
+                    assert_eq!(centre, (x, y), "{}", geometry);
+                }
+            }
+        }
+        // Where two hexagon rows overlap, the nearer centre wins.
+        assert_eq!(Geometry::Hexagonal.cell_at(0.5, 1.05), (0, 0));
+        assert_eq!(Geometry::Hexagonal.cell_at(0.05, 1.05), (-1, 1));
+        // The corners of an upward triangle belong to its neighbours.
+        assert_eq!(Geometry::Triangular.cell_at(0.1, 0.1), (-1, 0));
+        assert_eq!(Geometry::Triangular.cell_at(0.9, 0.1), (1, 0));
+    }
+
+    #[test]
+    fn fit_rounds_wrapped_sides_up() {
+        let hex = Geometry::Hexagonal;
+        let triangle = Geometry::Triangular;
+        assert_eq!(hex.fit(Topology::Torus, 9, 7), (9, 8));
+        assert_eq!(hex.fit(Topology::Plane, 9, 7), (9, 7));
+        assert_eq!(triangle.fit(Topology::Torus, 9, 7), (10, 8));
+        assert_eq!(triangle.fit(Topology::ShiftedTorus(1), 9, 7), (10, 7));
+        assert!(!hex.fits(Topology::Sphere, 8, 8));
+        assert!(Geometry::Square.fits(Topology::Sphere, 7, 7));
+    }
+}
@@ -0,0 +1,583 @@
+use std::collections::HashMap;
+
+use crate::{
+    engine::{Engine, Rect},
+    rule::{neighbourhood_bit, Rule},
+    universe::Topology,
+};
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+const MIN_ROOT_LEVEL: u8 = 3;
+/// Keeps every coordinate inside an `i64`.
+const MAX_ROOT_LEVEL: u8 = 62;
+/// Rough cost of a node including its entries in the lookup tables.
+const BYTES_PER_NODE: usize = 96;
+
+#[derive(Clone, Copy)]
+struct Node {
+    nw: NodeId,
+    ne: NodeId,
+    sw: NodeId,
+    se: NodeId,
+    level: u8,
+    population: u64,
+}
+
+/// Gosper's HashLife on an unbounded plane. The universe is a quadtree of
+/// canonicalised nodes, and the centre of each node a power of two
+/// generations ahead is memoised, so repetitive patterns can be advanced by
+/// enormous steps.
+pub struct HashLife {
+    nodes: Vec<Node>,
+    index: HashMap<(NodeId, NodeId, NodeId, NodeId), NodeId>,
+    /// Result of `(node, log2 step)`.
+    results: HashMap<(NodeId, u8), NodeId>,
+    empty: Vec<NodeId>,
+    root: NodeId,
+    max_nodes: usize,
+
+    rule: Rule,
+    generation: u64,
+}
+
+impl HashLife {
+    /// Creates an empty universe that collects garbage once its nodes would
+    /// use more than roughly `memory_limit` bytes.
+    pub fn new(memory_limit: usize) -> Self {
+        let leaf = |population| Node {
+            nw: DEAD,
+            ne: DEAD,
+            sw: DEAD,
+            se: DEAD,
+            level: 0,
+            population,
+        };
+        let mut hashlife = Self {
+            nodes: vec![leaf(0), leaf(1)],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            max_nodes: 0,
+
+            rule: Rule::default(),
+            generation: 0,
+        };
+        hashlife.set_memory_limit(memory_limit);
+        hashlife.root = hashlife.empty(MIN_ROOT_LEVEL);
+        hashlife
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        if let Some(&id) = self.index.get(&(nw, ne, sw, se)) {
+            return id;
+        }
+        let population = [nw, ne, sw, se].iter().fold(0u64, |sum, &n| {
+            sum.saturating_add(self.nodes[n as usize].population)
+        });
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            nw,
+            ne,
+            sw,
+            se,
+            level: self.nodes[nw as usize].level + 1,
+            population,
+        });
+        self.index.insert((nw, ne, sw, se), id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let next = self.join(e, e, e, e);
+            self.empty.push(next);
+        }
+        self.empty[level as usize]
+    }
+
+    fn node(&self, id: NodeId) -> Node {
+        self.nodes[id as usize]
+    }
+
+    /// Half the width of the root, which is centred on the origin.
+    fn root_half(&self) -> i64 {
+        1 << (self.node(self.root).level - 1)
+    }
+
+    /// Surrounds a node with empty space, doubling its width.
+    fn expand(&mut self, id: NodeId) -> NodeId {
+        let n = self.node(id);
+        let e = self.empty(n.level - 1);
+        let nw = self.join(e, e, e, n.nw);
+        let ne = self.join(e, e, n.ne, e);
+        let sw = self.join(e, n.sw, e, e);
+        let se = self.join(n.se, e, e, e);
+        self.join(nw, ne, sw, se)
+    }
+
+    fn centre(&mut self, id: NodeId) -> NodeId {
+        let n = self.node(id);
+        let (nw, ne, sw, se) = (
+            self.node(n.nw),
+            self.node(n.ne),
+            self.node(n.sw),
+            self.node(n.se),
+        );
+        self.join(nw.se, ne.sw, sw.ne, se.nw)
+    }
+
+    fn centre_horizontal(&mut self, west: NodeId, east: NodeId) -> NodeId {
+        let (w, e) = (self.node(west), self.node(east));
+        self.join(w.ne, e.nw, w.se, e.sw)
+    }
+
+    fn centre_vertical(&mut self, north: NodeId, south: NodeId) -> NodeId {
+        let (n, s) = (self.node(north), self.node(south));
+        self.join(n.sw, n.se, s.nw, s.ne)
+    }
+
+    /// Whether all live cells lie in the central half of the node.
+    fn is_padded(&self, id: NodeId) -> bool {
+        let n = self.node(id);
+        let inner = [
+            self.node(n.nw).se,
+            self.node(n.ne).sw,
+            self.node(n.sw).ne,
+            self.node(n.se).nw,
+        ];
+        inner.iter().map(|&i| self.node(i).population).sum::<u64>() == n.population
+    }
+
+    /// Advances a 4x4 node by one generation, returning its central 2x2.
+    fn step_base(&mut self, id: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = self.cell_in(id, x as i64, y as i64);
+            }
+        }
+
+        let mut next = [DEAD; 4];
+        for (i, (x, y)) in [(1, 1), (2, 1), (1, 2), (2, 2)].into_iter().enumerate() {
+            let mut neighbourhood = 0;
+            for dy in -1..=1i32 {
+                for dx in -1..=1i32 {
+                    if (dx, dy) != (0, 0)
+                        && cells[(y as i32 + dy) as usize][(x as i32 + dx) as usize]
+                    {
+                        neighbourhood |= neighbourhood_bit(dx, dy);
+                    }
+                }
+            }
+            next[i] = self.rule.transition(cells[y][x] as u32, neighbourhood);
+        }
+        self.join(next[0], next[1], next[2], next[3])
+    }
+
+    /// Returns the central half of a node `2^step_log2` generations later,
+    /// or `2^(level - 2)` generations later if that is smaller.
+    fn next(&mut self, id: NodeId, step_log2: u8) -> NodeId {
+        let n = self.node(id);
+        let step_log2 = step_log2.min(n.level - 2);
+        if n.population == 0 {
+            return self.empty(n.level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, step_log2)) {
+            return result;
+        }
+
+        let result = if n.level == 2 {
+            self.step_base(id)
+        } else {
+            let n00 = n.nw;
+            let n01 = self.centre_horizontal(n.nw, n.ne);
+            let n02 = n.ne;
+            let n10 = self.centre_vertical(n.nw, n.sw);
+            let n11 = self.centre(id);
+            let n12 = self.centre_vertical(n.ne, n.se);
+            let n20 = n.sw;
+            let n21 = self.centre_horizontal(n.sw, n.se);
+            let n22 = n.se;
+
+            // At full speed both halves of the step advance; otherwise the
+            // first half only re-centres and the second does all the work.
+            let full_speed = step_log2 == n.level - 2;
+            let first_half = |hashlife: &mut Self, node| {
+                if full_speed {
+                    hashlife.next(node, step_log2)
+                } else {
+                    hashlife.centre(node)
+                }
+            };
+            let r =
+                [n00, n01, n02, n10, n11, n12, n20, n21, n22].map(|node| first_half(self, node));
+
+            let a = self.join(r[0], r[1], r[3], r[4]);
+            let b = self.join(r[1], r[2], r[4], r[5]);
+            let c = self.join(r[3], r[4], r[6], r[7]);
+            let d = self.join(r[4], r[5], r[7], r[8]);
+            let (a, b, c, d) = (
+                self.next(a, step_log2),
+                self.next(b, step_log2),
+                self.next(c, step_log2),
+                self.next(d, step_log2),
+            );
+            self.join(a, b, c, d)
+        };
+
+        self.results.insert((id, step_log2), result);
+        result
+    }
+
+    /// Advances the whole universe by `2^step_log2` generations.
+    fn advance(&mut self, step_log2: u8) {
+        if self.nodes.len() > self.max_nodes {
+            self.collect_garbage();
+        }
+
+        while self.node(self.root).level < step_log2 + 2 || !self.is_padded(self.root) {
+            if self.node(self.root).level >= MAX_ROOT_LEVEL {
+                log::warn!("HashLife universe reached its maximum size");
+                return;
+            }
+            self.root = self.expand(self.root);
+        }
+        self.root = self.expand(self.root);
+        self.root = self.next(self.root, step_log2);
+        self.generation = self.generation.wrapping_add(1u64 << step_log2);
+    }
+
+    /// Drops every node unreachable from the root and all memoised results.
+    fn collect_garbage(&mut self) {
+        let mut remap: HashMap<NodeId, NodeId> = HashMap::new();
+        remap.insert(DEAD, DEAD);
+        remap.insert(ALIVE, ALIVE);
+        let mut nodes = vec![self.nodes[DEAD as usize], self.nodes[ALIVE as usize]];
+        let mut index = HashMap::new();
+
+        let mut stack = vec![(self.root, false)];
+        while let Some((id, children_done)) = stack.pop() {
+            if remap.contains_key(&id) {
+                continue;
+            }
+            let n = self.node(id);
+            if !children_done {
+                stack.push((id, true));
+                stack.extend([n.nw, n.ne, n.sw, n.se].map(|c| (c, false)));
+                continue;
+            }
+            let node = Node {
+                nw: remap[&n.nw],
+                ne: remap[&n.ne],
+                sw: remap[&n.sw],
+                se: remap[&n.se],
+                ..n
+            };
+            let new_id = nodes.len() as NodeId;
+            nodes.push(node);
+            index.insert((node.nw, node.ne, node.sw, node.se), new_id);
+            remap.insert(id, new_id);
+        }
+
+        log::debug!(
+            "HashLife garbage collection: {} -> {} nodes",
+            self.nodes.len(),
+            nodes.len()
+        );
+        self.root = remap[&self.root];
+        self.nodes = nodes;
+        self.index = index;
+        self.results.clear();
+        self.empty = vec![DEAD];
+        if self.nodes.len() > self.max_nodes {
+            log::warn!("HashLife pattern alone exceeds the memory limit");
+        }
+    }
+
+    /// Reads a cell of a node using coordinates relative to its top-left corner.
+    fn cell_in(&self, mut id: NodeId, mut x: i64, mut y: i64) -> bool {
+        loop {
+            let n = self.node(id);
+            if n.level == 0 {
+                return id == ALIVE;
+            }
+            let half = 1 << (n.level - 1);
+            id = match (x >= half, y >= half) {
+                (false, false) => n.nw,
+                (true, false) => n.ne,
+                (false, true) => n.sw,
+                (true, true) => n.se,
+            };
+            x %= half;
+            y %= half;
+        }
+    }
+
+    fn set_in(&mut self, id: NodeId, x: i64, y: i64, alive: bool) -> NodeId {
+        let n = self.node(id);
+        if n.level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+        let half = 1 << (n.level - 1);
+        let (mut nw, mut ne, mut sw, mut se) = (n.nw, n.ne, n.sw, n.se);
+        match (x >= half, y >= half) {
+            (false, false) => nw = self.set_in(nw, x, y, alive),
+            (true, false) => ne = self.set_in(ne, x - half, y, alive),
+            (false, true) => sw = self.set_in(sw, x, y - half, alive),
+            (true, true) => se = self.set_in(se, x - half, y - half, alive),
+        }
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Calls `f` with the world position of every live cell of `id` that
+    /// lies in `clip`, where the node's top-left corner is at `(x, y)`.
+    fn visit_live(&self, id: NodeId, x: i64, y: i64, clip: &Rect, f: &mut impl FnMut(i64, i64)) {
+        let n = self.node(id);
+        if n.population == 0 {
+            return;
+        }
+        let size = 1i64 << n.level;
+        if x + size <= clip.x
+            || y + size <= clip.y
+            || x >= clip.x + clip.width as i64
+            || y >= clip.y + clip.height as i64
+        {
+            return;
+        }
+        if n.level == 0 {
+            f(x, y);
+            return;
+        }
+        let half = size / 2;
+        self.visit_live(n.nw, x, y, clip, f);
+        self.visit_live(n.ne, x + half, y, clip, f);
+        self.visit_live(n.sw, x, y + half, clip, f);
+        self.visit_live(n.se, x + half, y + half, clip, f);
+    }
+
+    /// Bounds of the live cells of a node relative to its top-left corner, as
+    /// `(min_x, min_y, max_x, max_y)`.
+    fn node_bounds(
+        &self,
+        id: NodeId,
+        cache: &mut HashMap<NodeId, Option<(i64, i64, i64, i64)>>,
+    ) -> Option<(i64, i64, i64, i64)> {
+        let n = self.node(id);
+        if n.population == 0 {
+            return None;
+        }
+        if n.level == 0 {
+            return Some((0, 0, 0, 0));
+        }
+        if let Some(&bounds) = cache.get(&id) {
+            return bounds;
+        }
+
+        let half = 1i64 << (n.level - 1);
+        let bounds = [
+            (n.nw, 0, 0),
+            (n.ne, half, 0),
+            (n.sw, 0, half),
+            (n.se, half, half),
+        ]
+        .into_iter()
+        .filter_map(|(child, dx, dy)| {
+            self.node_bounds(child, cache)
+                .map(|(x0, y0, x1, y1)| (x0 + dx, y0 + dy, x1 + dx, y1 + dy))
+        })
+        .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)));
+        cache.insert(id, bounds);
+        bounds
+    }
+
+    fn root_rect(&self) -> Rect {
+        let half = self.root_half();
+        Rect {
+            x: -half,
+            y: -half,
+            width: 2 * half as u64,
+            height: 2 * half as u64,
+        }
+    }
+}
+
+impl Engine for HashLife {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String> {
+        if rule.states() > 2 || rule.range_rule().is_some() {
+            return Err(
+                "the hashlife backend only runs two-state rules on the eight nearest neighbours"
+                    .to_string(),
+            );
+        }
+        if rule.is_b0() {
+            return Err("the hashlife backend can't run B0 rules".to_string());
+        }
+        self.rule = rule;
+        self.results.clear();
+        Ok(())
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        None
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn get(&self, x: i64, y: i64) -> u32 {
+        let half = self.root_half();
+        if !(-half..half).contains(&x) || !(-half..half).contains(&y) {
+            return 0;
+        }
+        self.cell_in(self.root, x + half, y + half) as u32
+    }
+
+    fn set(&mut self, x: i64, y: i64, state: u32) {
+        loop {
+            let half = self.root_half();
+            if (-half..half).contains(&x) && (-half..half).contains(&y) {
+                self.root = self.set_in(self.root, x + half, y + half, state > 0);
+                return;
+            }
+            if self.node(self.root).level >= MAX_ROOT_LEVEL {
+                return;
+            }
+            self.root = self.expand(self.root);
+        }
+    }
+
+    fn step(&mut self) {
+        self.advance(0);
+    }
+
+    /// Advances by each power of two in `n`.
+    fn step_n(&mut self, n: u64) {
+        for step_log2 in 0..u64::BITS as u8 {
+            if n & (1u64 << step_log2) != 0 {
+                self.advance(step_log2);
+            }
+        }
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u32)> {
+        let mut cells = Vec::new();
+        let half = self.root_half();
+        self.visit_live(self.root, -half, -half, &self.root_rect(), &mut |x, y| {
+            cells.push((x, y, 1))
+        });
+        cells
+    }
+
+    fn population(&self) -> u64 {
+        self.node(self.root).population
+    }
+
+    fn bounding_box(&self) -> Option<Rect> {
+        let half = self.root_half();
+        self.node_bounds(self.root, &mut HashMap::new())
+            .map(|(x0, y0, x1, y1)| Rect {
+                x: x0 - half,
+                y: y0 - half,
+                width: (x1 - x0 + 1) as u64,
+                height: (y1 - y0 + 1) as u64,
+            })
+    }
+
+    fn read_region(&self, rect: Rect, out: &mut [u32]) {
+        out.fill(0);
+        let half = self.root_half();
+        self.visit_live(self.root, -half, -half, &rect, &mut |x, y| {
+            out[((y - rect.y) as u64 * rect.width + (x - rect.x) as u64) as usize] = 1;
+        });
+    }
+
+    fn clear(&mut self) {
+        self.root = self.empty(MIN_ROOT_LEVEL);
+    }
+
+    fn set_memory_limit(&mut self, bytes: usize) {
+        self.max_nodes = (bytes / BYTES_PER_NODE).max(1024);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        engine::{Backend, DEFAULT_MEMORY_LIMIT},
+        universe::Topology,
+    };
+
+    /// Live cells relative to the top-left of their bounding box, sorted.
+    fn normalised(engine: &dyn Engine) -> Vec<(i64, i64, u32)> {
+        let Some(bbox) = engine.bounding_box() else {
+            return Vec::new();
+        };
+        let mut cells = engine
+            .live_cells()
+            .into_iter()
+            .map(|(x, y, state)| (x - bbox.x, y - bbox.y, state))
+            .collect::<Vec<_>>();
+        cells.sort_unstable();
+        cells
+    }
+
+    #[test]
+    fn matches_the_dense_engine() {
+        // A fixed 16x16 soup from a linear congruential generator.
+        let mut seed = 1u32;
+        let soup = (0..256i64)
+            .filter(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                seed >> 30 == 0
+            })
+            .map(|i| (i % 16, i / 16))
+            .collect::<Vec<_>>();
+        for (rule, generations) in [("B3/S23", 200), ("B36/S23", 150), ("B2-a/S12", 40)] {
+            let mut dense = Backend::Dense.create(256, 256, Topology::Plane).unwrap();
+            let mut hashlife = Backend::HashLife.create(0, 0, Topology::Plane).unwrap();
+            for engine in [&mut dense, &mut hashlife] {
+                engine.set_rule(rule.parse().unwrap()).unwrap();
+                for &(x, y) in &soup {
+                    engine.set(x + 120, y + 120, 1);
+                }
+            }
+            dense.step_n(generations);
+            hashlife.step_n(generations);
+            assert_eq!(hashlife.population(), dense.population(), "{}", rule);
+            let size = |engine: &dyn Engine| engine.bounding_box().map(|b| (b.width, b.height));
+            assert_eq!(size(hashlife.as_ref()), size(dense.as_ref()), "{}", rule);
+            assert_eq!(
+                normalised(hashlife.as_ref()),
+                normalised(dense.as_ref()),
+                "{}",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_b0_rules() {
+        let mut hashlife = HashLife::new(DEFAULT_MEMORY_LIMIT);
+        assert!(hashlife.set_rule("B0/S8".parse().unwrap()).is_err());
+        assert!(hashlife.set_rule("B3/S23".parse().unwrap()).is_ok());
+    }
+}
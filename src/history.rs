@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+
+use game_of_life::{Engine, Rule};
+
+/// Memory the undo and redo stacks may use together.
+pub const HISTORY_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Replaces the contents of a universe with `cells` at `generation`.
+pub fn restore(universe: &mut dyn Engine, cells: &[(i64, i64, u32)], generation: u64) {
+    universe.clear();
+    for &(x, y, state) in cells {
+        universe.set(x, y, state);
+    }
+    universe.set_generation(generation);
+}
+
+/// The live cells, generation and rule of a universe at one point in time.
+struct Snapshot {
+    cells: Vec<(i64, i64, u32)>,
+    generation: u64,
+    rule: Rule,
+}
+
+impl Snapshot {
+    fn take(universe: &dyn Engine) -> Self {
+        Self {
+            cells: universe.live_cells(),
+            generation: universe.generation(),
+            rule: universe.rule().clone(),
+        }
+    }
+
+    fn restore(&self, universe: &mut dyn Engine) {
+        if universe.rule() != &self.rule {
+            // The universe was running this rule when the snapshot was taken.
+            let _ = universe.set_rule(self.rule.clone());
+        }
+        restore(universe, &self.cells, self.generation);
+    }
+
+    fn size(&self) -> usize {
+        self.cells.len() * std::mem::size_of::<(i64, i64, u32)>() + std::mem::size_of::<Self>()
+    }
+}
+
+/// Undo and redo stacks of whole-universe snapshots. The oldest undo steps
+/// are forgotten once the snapshots exceed the memory limit.
+pub struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    used: usize,
+    memory_limit: usize,
+}
+
+impl History {
+    pub fn new(memory_limit: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            used: 0,
+            memory_limit,
+        }
+    }
+
+    /// Saves the universe before an operation that changes it.
+    pub fn record(&mut self, universe: &dyn Engine) {
+        self.used -= self.redo.drain(..).map(|s| s.size()).sum::<usize>();
+        self.push_undo(Snapshot::take(universe));
+    }
+
+    /// Restores the state before the last recorded operation. Returns false
+    /// if there is nothing to undo.
+    pub fn undo(&mut self, universe: &mut dyn Engine) -> bool {
+        let Some(snapshot) = self.undo.pop_back() else {
+            return false;
+        };
+        self.used -= snapshot.size();
+        let current = Snapshot::take(universe);
+        self.used += current.size();
+        self.redo.push(current);
+        snapshot.restore(universe);
+        self.trim();
+        true
+    }
+
+    /// Reapplies the last undone operation. Returns false if there is
+    /// nothing to redo.
+    pub fn redo(&mut self, universe: &mut dyn Engine) -> bool {
+        let Some(snapshot) = self.redo.pop() else {
+            return false;
+        };
+        self.used -= snapshot.size();
+        self.push_undo(Snapshot::take(universe));
+        snapshot.restore(universe);
+        true
+    }
+
+    fn push_undo(&mut self, snapshot: Snapshot) {
+        self.used += snapshot.size();
+        self.undo.push_back(snapshot);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.used > self.memory_limit {
+            match self.undo.pop_front() {
+                Some(snapshot) => self.used -= snapshot.size(),
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game_of_life::{Backend, Topology};
+
+    use super::*;
+
+    fn universe() -> Box<dyn Engine> {
+        Backend::Dense.create(16, 16, Topology::Torus).unwrap()
+    }
+
+    #[test]
+    fn undoes_and_redoes_edits() {
+        let mut universe = universe();
+        let mut history = History::new(HISTORY_MEMORY_LIMIT);
+        assert!(!history.undo(universe.as_mut()));
+
+        history.record(universe.as_ref());
+        universe.set(1, 1, 1);
+        history.record(universe.as_ref());
+        universe.set(2, 2, 1);
+        universe.set_generation(5);
+
+        assert!(history.undo(universe.as_mut()));
+        assert_eq!(universe.live_cells(), [(1, 1, 1)]);
+        assert_eq!(universe.generation(), 0);
+        assert!(history.undo(universe.as_mut()));
+        assert_eq!(universe.population(), 0);
+        assert!(!history.undo(universe.as_mut()));
+
+        assert!(history.redo(universe.as_mut()));
+        assert!(history.redo(universe.as_mut()));
+        assert_eq!(universe.live_cells(), [(1, 1, 1), (2, 2, 1)]);
+        assert_eq!(universe.generation(), 5);
+        assert!(!history.redo(universe.as_mut()));
+
+        // A new edit forgets what could be redone.
+        history.undo(universe.as_mut());
+        history.record(universe.as_ref());
+        assert!(!history.redo(universe.as_mut()));
+    }
+
+    #[test]
+    fn undo_restores_the_rule() {
+        let mut universe = universe();
+        let mut history = History::new(HISTORY_MEMORY_LIMIT);
+        history.record(universe.as_ref());
+        universe.set_rule("B36/S23".parse().unwrap()).unwrap();
+        universe.set(3, 3, 1);
+        history.undo(universe.as_mut());
+        assert_eq!(universe.rule(), &Rule::LIFE);
+        history.redo(universe.as_mut());
+        assert_eq!(universe.rule().to_string(), "B36/S23");
+    }
+
+    #[test]
+    fn forgets_the_oldest_steps_past_the_memory_limit() {
+        let mut universe = universe();
+        for x in 0..10 {
+            universe.set(x, 0, 1);
+        }
+        let size = Snapshot::take(universe.as_ref()).size();
+        let mut history = History::new(size * 3);
+        for y in 1..=5 {
+            history.record(universe.as_ref());
+            universe.set(0, y, 1);
+        }
+        assert!(history.used <= size * 3);
+        let mut undone = 0;
+        while history.undo(universe.as_mut()) {
+            undone += 1;
+        }
+        assert_eq!(undone, 2);
+        assert_eq!(universe.population(), 13);
+    }
+}
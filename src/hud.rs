@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+use wgpu_glyph::{ab_glyph::FontArc, GlyphBrush, GlyphBrushBuilder, Section, Text};
+
+const FONT: &[u8] = include_bytes!("../assets/Inconsolata-Regular.ttf");
+const FONT_SIZE: f32 = 18.0;
+const MARGIN: f32 = 8.0;
+/// How often the generations per second figure is recomputed.
+const RATE_WINDOW: Duration = Duration::from_millis(500);
+
+/// A text overlay in the top-left corner of the window, with the frame and
+/// generation rates it reports.
+pub struct Hud {
+    brush: GlyphBrush<()>,
+    staging_belt: wgpu::util::StagingBelt,
+    pub visible: bool,
+
+    last_frame: Instant,
+    frame_time: Duration,
+    rate_start: (Instant, u64),
+    generations_per_second: f64,
+}
+
+impl Hud {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let font = FontArc::try_from_slice(FONT).expect("bundled font is valid");
+        let now = Instant::now();
+        Self {
+            brush: GlyphBrushBuilder::using_font(font).build(device, format),
+            staging_belt: wgpu::util::StagingBelt::new(1024),
+            visible: true,
+
+            last_frame: now,
+            frame_time: Duration::ZERO,
+            rate_start: (now, 0),
+            generations_per_second: 0.0,
+        }
+    }
+
+    /// Updates the frame time and generation rate. Call once per frame.
+    pub fn tick(&mut self, generation: u64) {
+        let now = Instant::now();
+        self.frame_time = now - self.last_frame;
+        self.last_frame = now;
+
+        let (start, start_generation) = self.rate_start;
+        let elapsed = now - start;
+        if generation < start_generation {
+            self.rate_start = (now, generation);
+        } else if elapsed >= RATE_WINDOW {
+            self.generations_per_second =
+                (generation - start_generation) as f64 / elapsed.as_secs_f64();
+            self.rate_start = (now, generation);
+        }
+    }
+
+    pub fn generations_per_second(&self) -> f64 {
+        self.generations_per_second
+    }
+
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+
+    /// Draws `lines` over whatever `encoder` has already rendered to `view`.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: winit::dpi::PhysicalSize<u32>,
+        lines: &str,
+    ) {
+        let bounds = (size.width as f32, size.height as f32);
+        // A dark copy offset by a pixel keeps the text readable over live cells.
+        for (offset, color) in [(1.0, [0.0, 0.0, 0.0, 1.0]), (0.0, [1.0, 0.9, 0.3, 1.0])] {
+            self.brush.queue(Section {
+                screen_position: (MARGIN + offset, MARGIN + offset),
+                bounds,
+                text: vec![Text::new(lines).with_color(color).with_scale(FONT_SIZE)],
+                ..Section::default()
+            });
+        }
+        if let Err(e) = self.brush.draw_queued(
+            device,
+            &mut self.staging_belt,
+            encoder,
+            view,
+            size.width,
+            size.height,
+        ) {
+            log::error!("Failed to draw HUD: {}", e);
+        }
+        self.staging_belt.finish();
+    }
+
+    /// Reclaims the staging buffers once the frame has been submitted.
+    pub fn recall(&mut self) {
+        async_std::task::spawn(self.staging_belt.recall());
+    }
+}
@@ -0,0 +1,14 @@
+pub mod engine;
+pub mod geometry;
+pub mod hashlife;
+pub mod packed;
+pub mod pattern;
+pub mod rule;
+pub mod sparse;
+pub mod universe;
+
+pub use engine::{Backend, Engine, Rect};
+pub use geometry::Geometry;
+pub use pattern::Pattern;
+pub use rule::Rule;
+pub use universe::{Anchor, Topology, Universe};
@@ -1,27 +1,57 @@
+use std::process;
+
+use config::Config;
 use game::Game;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Fullscreen, WindowBuilder},
 };
 
+mod camera;
 mod cell;
+mod clipboard;
+mod config;
 mod game;
+mod history;
+mod hud;
+mod rewind;
+
+/// The largest initial window, for grids too big to show whole.
+const MAX_WINDOW_SIZE: (u32, u32) = (1920, 1080);
 
 #[async_std::main]
 async fn main() {
     env_logger::init();
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    });
+
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
-        .with_title("Test")
+    let mut window_builder = WindowBuilder::new()
+        .with_title("Game of Life")
         .with_position(PhysicalPosition::<u32>::new(0, 0))
-        .with_inner_size(PhysicalSize::<u32>::new(1280, 720))
-        // .with_fullscreen(Some(Fullscreen::Borderless(None)))
-        .build(&event_loop)
-        .unwrap();
+        .with_inner_size(PhysicalSize::<u32>::new(
+            config
+                .width
+                .saturating_mul(config.cell_size)
+                .min(MAX_WINDOW_SIZE.0),
+            config
+                .height
+                .saturating_mul(config.cell_size)
+                .min(MAX_WINDOW_SIZE.1),
+        ));
+    if config.fullscreen {
+        window_builder = window_builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    let window = window_builder.build(&event_loop).unwrap();
 
-    let mut game = Game::new(&window).await;
+    let mut game = Game::new(&window, &config).await.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    });
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
@@ -32,9 +62,8 @@ async fn main() {
                 window_id,
             } if window_id == window.id() => {
                 game.input(event);
-                match event {
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    _ => {}
+                if let WindowEvent::CloseRequested = event {
+                    *control_flow = ControlFlow::Exit
                 }
             }
 
@@ -0,0 +1,347 @@
+use rayon::prelude::*;
+
+use crate::{
+    engine::{Engine, Rect},
+    rule::Rule,
+    universe::{Anchor, Topology},
+};
+
+const WORD_BITS: u32 = u64::BITS;
+
+/// A two-state grid with 64 cells packed into each `u64`. Neighbours are
+/// counted for a whole word at once by summing shifted copies of the
+/// surrounding rows with bitwise full adders. Only the torus and plane
+/// topologies are supported; `Backend::create` rejects the others.
+pub struct PackedUniverse {
+    width: u32,
+    height: u32,
+    words_per_row: usize,
+    rule: Rule,
+    topology: Topology,
+    generation: u64,
+    parallel: bool,
+
+    current: Vec<u64>,
+    next: Vec<u64>,
+}
+
+fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let ab = a ^ b;
+    (ab ^ c, (a & b) | (c & ab))
+}
+
+fn half_add(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+/// Applies the rule to 64 cells at once. Each triple holds the west, centre
+/// and east shifted copies of the row above, the cells' own row and the row
+/// below, as returned by `PackedUniverse::shifts`.
+pub(crate) fn next_word(
+    above: (u64, u64, u64),
+    row: (u64, u64, u64),
+    below: (u64, u64, u64),
+    birth: &[u32],
+    survival: &[u32],
+) -> u64 {
+    let ((aw, ac, ae), (w, alive, e), (bw, bc, be)) = (above, row, below);
+
+    // Sum the eight neighbour bits into a four bit count per cell.
+    let (s0, c0) = full_add(aw, ac, ae);
+    let (s1, c1) = full_add(w, e, bw);
+    let (s2, c2) = half_add(bc, be);
+    let (ones, c3) = full_add(s0, s1, s2);
+    let (t0, t1) = full_add(c0, c1, c2);
+    let (twos, t2) = half_add(t0, c3);
+    let fours = t1 ^ t2;
+    let eights = t1 & t2;
+
+    let count_is = |n: u32| {
+        let select = |bit: u32, word: u64| if n & bit != 0 { word } else { !word };
+        select(1, ones) & select(2, twos) & select(4, fours) & select(8, eights)
+    };
+    let born = birth.iter().fold(0, |acc, &n| acc | count_is(n));
+    let survives = survival.iter().fold(0, |acc, &n| acc | count_is(n));
+
+    (!alive & born) | (alive & survives)
+}
+
+impl PackedUniverse {
+    pub fn new(width: u32, height: u32, topology: Topology) -> Self {
+        let words_per_row = width.div_ceil(WORD_BITS) as usize;
+        let len = words_per_row * height as usize;
+        Self {
+            width,
+            height,
+            words_per_row,
+            rule: Rule::default(),
+            topology,
+            generation: 0,
+            parallel: true,
+
+            current: vec![0; len],
+            next: vec![0; len],
+        }
+    }
+
+    /// Mask of the bits in a row's last word that hold real cells.
+    fn last_word_mask(&self) -> u64 {
+        match self.width % WORD_BITS {
+            0 => !0,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    fn position(&self, x: i64, y: i64) -> Option<(usize, u32)> {
+        let (x, y) = self
+            .topology
+            .wrap(x, y, self.width as i64, self.height as i64)?;
+        let word = y as usize * self.words_per_row + (x as u32 / WORD_BITS) as usize;
+        Some((word, x as u32 % WORD_BITS))
+    }
+
+    fn row(&self, y: i64) -> Option<&[u64]> {
+        let y = match self.topology {
+            Topology::Torus => y.rem_euclid(self.height as i64),
+            _ if (0..self.height as i64).contains(&y) => y,
+            _ => return None,
+        } as usize;
+        Some(&self.current[y * self.words_per_row..(y + 1) * self.words_per_row])
+    }
+
+    fn bit(row: &[u64], x: u32) -> u64 {
+        (row[(x / WORD_BITS) as usize] >> (x % WORD_BITS)) & 1
+    }
+
+    /// Returns word `i` of `row` together with copies shifted so that bit `b`
+    /// holds the cell to the west and to the east of cell `b`.
+    fn shifts(&self, row: &[u64], i: usize) -> (u64, u64, u64) {
+        let torus = self.topology == Topology::Torus;
+        let centre = row[i];
+        let west_carry = if i > 0 {
+            row[i - 1] >> (WORD_BITS - 1)
+        } else if torus {
+            Self::bit(row, self.width - 1)
+        } else {
+            0
+        };
+        let east_carry = if i + 1 < self.words_per_row {
+            (row[i + 1] & 1) << (WORD_BITS - 1)
+        } else if torus {
+            Self::bit(row, 0) << ((self.width - 1) % WORD_BITS)
+        } else {
+            0
+        };
+        (
+            (centre << 1) | west_carry,
+            centre,
+            (centre >> 1) | east_carry,
+        )
+    }
+
+    fn step_row(&self, y: usize, out: &mut [u64], birth: &[u32], survival: &[u32]) {
+        let above = self.row(y as i64 - 1);
+        let row = self.row(y as i64).unwrap();
+        let below = self.row(y as i64 + 1);
+        let last_word_mask = self.last_word_mask();
+
+        for (i, next) in out.iter_mut().enumerate() {
+            *next = next_word(
+                above.map_or((0, 0, 0), |r| self.shifts(r, i)),
+                self.shifts(row, i),
+                below.map_or((0, 0, 0), |r| self.shifts(r, i)),
+                birth,
+                survival,
+            );
+            if i + 1 == self.words_per_row {
+                *next &= last_word_mask;
+            }
+        }
+    }
+}
+
+impl Engine for PackedUniverse {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String> {
+        if !rule.is_life_like() {
+            return Err("the packed backend only runs Life-like rules".to_string());
+        }
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        Some(self.topology)
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn get(&self, x: i64, y: i64) -> u32 {
+        self.position(x, y)
+            .map_or(0, |(word, bit)| ((self.current[word] >> bit) & 1) as u32)
+    }
+
+    fn set(&mut self, x: i64, y: i64, state: u32) {
+        if let Some((word, bit)) = self.position(x, y) {
+            if state > 0 {
+                self.current[word] |= 1 << bit;
+            } else {
+                self.current[word] &= !(1 << bit);
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        let birth = (0..9).filter(|&n| self.rule.birth(n)).collect::<Vec<_>>();
+        let survival = (0..9)
+            .filter(|&n| self.rule.survival(n))
+            .collect::<Vec<_>>();
+        let mut next = std::mem::take(&mut self.next);
+        let words_per_row = self.words_per_row.max(1);
+        if self.parallel {
+            next.par_chunks_mut(words_per_row)
+                .enumerate()
+                .for_each(|(y, out)| self.step_row(y, out, &birth, &survival));
+        } else {
+            next.chunks_mut(words_per_row)
+                .enumerate()
+                .for_each(|(y, out)| self.step_row(y, out, &birth, &survival));
+        }
+
+        self.next = std::mem::replace(&mut self.current, next);
+        self.generation += 1;
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u32)> {
+        let mut cells = Vec::new();
+        for (index, &word) in self.current.iter().enumerate() {
+            let y = (index / self.words_per_row) as i64;
+            let x0 = ((index % self.words_per_row) as u32 * WORD_BITS) as i64;
+            let mut bits = word;
+            while bits != 0 {
+                cells.push((x0 + bits.trailing_zeros() as i64, y, 1));
+                bits &= bits - 1;
+            }
+        }
+        cells
+    }
+
+    fn population(&self) -> u64 {
+        self.current.iter().map(|w| w.count_ones() as u64).sum()
+    }
+
+    fn read_region(&self, rect: Rect, out: &mut [u32]) {
+        let whole_grid = Rect {
+            x: 0,
+            y: 0,
+            width: self.width as u64,
+            height: self.height as u64,
+        };
+        if rect != whole_grid {
+            for (index, state) in out.iter_mut().enumerate() {
+                let x = rect.x + (index as u64 % rect.width) as i64;
+                let y = rect.y + (index as u64 / rect.width) as i64;
+                *state = self.get(x, y);
+            }
+            return;
+        }
+
+        for (y, row) in out.chunks_mut(self.width.max(1) as usize).enumerate() {
+            let words = &self.current[y * self.words_per_row..(y + 1) * self.words_per_row];
+            for (x, state) in row.iter_mut().enumerate() {
+                *state = Self::bit(words, x as u32) as u32;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.current.fill(0);
+    }
+
+    fn resize(&mut self, width: u32, height: u32, anchor: Anchor) {
+        let (offset_x, offset_y) = match anchor {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Centre => (
+                (width as i64 - self.width as i64) / 2,
+                (height as i64 - self.height as i64) / 2,
+            ),
+        };
+
+        let cells = self.live_cells();
+        let mut resized = Self::new(width, height, self.topology);
+        for (x, y, _) in cells {
+            let (x, y) = (x + offset_x, y + offset_y);
+            if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                resized.set(x, y, 1);
+            }
+        }
+
+        self.width = width;
+        self.height = height;
+        self.words_per_row = resized.words_per_row;
+        self.current = resized.current;
+        self.next = resized.next;
+    }
+
+    fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::universe::Universe;
+
+    #[test]
+    fn matches_the_dense_engine() {
+        // Widths on, just past and between word boundaries.
+        for (width, height) in [(64, 40), (100, 37), (129, 20), (7, 9)] {
+            for topology in [Topology::Torus, Topology::Plane] {
+                for rule in ["B3/S23", "B36/S23", "B2/S", "B3678/S34678"] {
+                    let mut dense = Universe::new(width, height);
+                    dense.set_topology(topology).unwrap();
+                    let mut packed = PackedUniverse::new(width, height, topology);
+                    let mut seed = width;
+                    for y in 0..height as i64 {
+                        for x in 0..width as i64 {
+                            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                            let state = (seed >> 30 == 0) as u32;
+                            dense.set(x, y, state);
+                            packed.set(x, y, state);
+                        }
+                    }
+                    for engine in [&mut dense as &mut dyn Engine, &mut packed] {
+                        engine.set_rule(rule.parse().unwrap()).unwrap();
+                    }
+                    for generation in 0..30 {
+                        dense.step();
+                        packed.step();
+                        assert_eq!(
+                            packed.live_cells(),
+                            dense.live_cells(),
+                            "{rule} on a {width}x{height} {topology} at {generation}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_runs_life_like_rules() {
+        let mut packed = PackedUniverse::new(8, 8, Topology::Torus);
+        assert!(packed.set_rule("B2/S/C3".parse().unwrap()).is_err());
+        assert!(packed.set_rule("B2-a/S12".parse().unwrap()).is_err());
+        assert!(packed.set_rule("B36/S23".parse().unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,218 @@
+use super::{Pattern, PatternError};
+use crate::rule::Rule;
+
+pub const HEADER_105: &str = "#Life 1.05";
+pub const HEADER_106: &str = "#Life 1.06";
+/// The widest gap a Life 1.05 block fills with dead cells.
+const MAX_GAP: i32 = 16;
+
+/// Parses a Life 1.06 file: one `x y` coordinate pair per live cell, with
+/// `#D` description lines.
+pub fn parse_106(text: &str) -> Result<Pattern, PatternError> {
+    let mut cells = Vec::new();
+    let mut comments = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix("#D") {
+            comments.push(comment.trim().to_string());
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut coords = line.split_whitespace().map(str::parse::<i64>);
+        match (coords.next(), coords.next(), coords.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => cells.push((x, y, 1)),
+            _ => {
+                return Err(PatternError::syntax(
+                    line_index + 1,
+                    format!("expected two integer coordinates, found '{}'", line),
+                ))
+            }
+        }
+    }
+    let mut pattern = Pattern::from_cells(cells)?;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+/// Parses a Life 1.05 file: `#P x y` blocks of `.`/`*` rows positioned
+/// relative to the pattern centre, with `#D` descriptions and `#N`/`#R` rules.
+pub fn parse_105(text: &str) -> Result<Pattern, PatternError> {
+    let mut cells = Vec::new();
+    let mut comments = Vec::new();
+    let mut rule = None;
+    let mut block: Option<(i64, i64)> = None;
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line_no = line_index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(HEADER_105) {
+            continue;
+        }
+
+        if let Some(directive) = line.strip_prefix('#') {
+            let mut chars = directive.chars();
+            let kind = chars.next();
+            let args = chars.as_str().trim();
+            match kind {
+                Some('D') | Some('C') => comments.push(args.to_string()),
+                Some('N') => rule = Some("B3/S23".to_string()),
+                Some('R') => rule = Some(args.to_string()),
+                Some('P') => {
+                    let mut coords = args.split_whitespace().map(str::parse::<i64>);
+                    match (coords.next(), coords.next(), coords.next()) {
+                        (Some(Ok(x)), Some(Ok(y)), None) => block = Some((x, y)),
+                        _ => {
+                            return Err(PatternError::syntax(
+                                line_no,
+                                format!("expected '#P x y', found '{}'", line),
+                            ))
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let (x0, y) = block
+            .as_mut()
+            .ok_or_else(|| PatternError::syntax(line_no, "cell row outside of a '#P' block"))?;
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                '*' | 'O' => cells.push((*x0 + x as i64, *y, 1)),
+                c => {
+                    return Err(PatternError::syntax(
+                        line_no,
+                        format!("unexpected character '{}', expected '.' or '*'", c),
+                    ))
+                }
+            }
+        }
+        *y += 1;
+    }
+
+    let mut pattern = Pattern::from_cells(cells)?;
+    pattern.comments = comments;
+    pattern.rule = rule;
+    Ok(pattern)
+}
+
+pub fn write_106(pattern: &Pattern) -> String {
+    let mut out = format!("{}\n", HEADER_106);
+    for comment in pattern.name.iter().chain(&pattern.comments) {
+        out.push_str(&format!("#D {}\n", comment));
+    }
+    for &(x, y, _) in &pattern.cells {
+        out.push_str(&format!("{} {}\n", x, y));
+    }
+    out
+}
+
+pub fn write_105(pattern: &Pattern) -> String {
+    let mut out = format!("{}\n", HEADER_105);
+    for comment in pattern.name.iter().chain(&pattern.comments) {
+        out.push_str(&format!("#D {}\n", comment));
+    }
+    match pattern.rule.as_deref().map(str::parse::<Rule>) {
+        Some(Ok(Rule::LIFE)) => out.push_str("#N\n"),
+        Some(Ok(rule)) if rule.is_life_like() => {
+            let counts = |f: &dyn Fn(u32) -> bool| {
+                (0..9)
+                    .filter(|&n| f(n))
+                    .map(|n| n.to_string())
+                    .collect::<String>()
+            };
+            out.push_str(&format!(
+                "#R {}/{}\n",
+                counts(&|n| rule.survival(n)),
+                counts(&|n| rule.birth(n))
+            ));
+        }
+        _ => {}
+    }
+    // Consecutive rows whose cells start close together share a block; other
+    // rows are split at wide gaps into blocks of their own, so empty space
+    // costs nothing.
+    let (x0, y0) = (-(pattern.width as i32 / 2), -(pattern.height as i32 / 2));
+    // Leftmost and rightmost start, first row and lines of each block.
+    type Block<'a> = (i32, i32, i32, Vec<&'a [(i32, u32)]>);
+    let mut blocks: Vec<Block> = Vec::new();
+    let rows = pattern.rows();
+    for (y, cells) in &rows {
+        let segments = cells
+            .chunk_by(|a, b| b.0 - a.0 <= MAX_GAP)
+            .collect::<Vec<_>>();
+        let start = cells[0].0;
+        match blocks.last_mut() {
+            Some((min, max, first_y, lines))
+                if segments.len() == 1
+                    && *first_y + lines.len() as i32 == *y
+                    && start.max(*max) - start.min(*min) <= MAX_GAP =>
+            {
+                (*min, *max) = (start.min(*min), start.max(*max));
+                lines.push(cells);
+            }
+            _ => blocks.extend(
+                segments
+                    .into_iter()
+                    .map(|segment| (segment[0].0, segment[0].0, *y, vec![segment])),
+            ),
+        }
+    }
+    for (min, _, first_y, lines) in blocks {
+        out.push_str(&format!("#P {} {}\n", x0 + min, y0 + first_y));
+        for line in lines {
+            let mut x = min;
+            for &(cell_x, _) in line {
+                out.extend((x..cell_x).map(|_| '.'));
+                out.push('*');
+                x = cell_x + 1;
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::Format;
+
+    #[test]
+    fn rejects_patterns_wider_than_i32() {
+        let text = "#Life 1.06\n-2000000000 0\n2000000000 0\n";
+        assert!(matches!(parse_106(text), Err(PatternError::TooLarge)));
+        let text = "#Life 1.06\n-1000000000 0\n1000000000 0\n";
+        assert_eq!(parse_106(text).unwrap().width, 2_000_000_001);
+    }
+
+    #[test]
+    fn saves_life_105_by_extension() {
+        assert_eq!(
+            Format::from_path(std::path::Path::new("glider.lif105")),
+            Format::Life105
+        );
+    }
+
+    #[test]
+    fn keeps_life_106_descriptions() {
+        let pattern = Pattern {
+            name: Some("Blinker".to_string()),
+            comments: vec!["population = 3".to_string()],
+            ..Pattern::from_cells([(0, 0, 1), (1, 0, 1), (2, 0, 1)]).unwrap()
+        };
+        let text = write_106(&pattern);
+        assert_eq!(
+            text,
+            "#Life 1.06\n#D Blinker\n#D population = 3\n0 0\n1 0\n2 0\n"
+        );
+        let parsed = parse_106(&text).unwrap();
+        assert_eq!(parsed.comments, ["Blinker", "population = 3"]);
+        assert_eq!(parsed.cells, pattern.cells);
+    }
+}
@@ -0,0 +1,389 @@
+use std::{fmt, fs, io, path::Path};
+
+use crate::rule::Rule;
+
+pub mod life;
+pub mod plaintext;
+pub mod rle;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Rle,
+    Plaintext,
+    Life105,
+    Life106,
+}
+
+impl Format {
+    /// Picks a format from a file extension, defaulting to RLE. Life 1.05
+    /// has no extension of its own, so it is written for `.lif105`.
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("cells") => Format::Plaintext,
+            Some("lif") | Some("life") => Format::Life106,
+            Some("lif105") => Format::Life105,
+            _ => Format::Rle,
+        }
+    }
+
+    /// Guesses the format of pattern text from its first line.
+    pub fn detect(text: &str) -> Self {
+        let first = text.lines().map(str::trim).find(|l| !l.is_empty());
+        match first {
+            Some(line) if line.starts_with(life::HEADER_105) => Format::Life105,
+            Some(line) if line.starts_with(life::HEADER_106) => Format::Life106,
+            Some(line) if line.starts_with('!') => Format::Plaintext,
+            Some(line) if line.chars().all(|c| matches!(c, '.' | 'O')) => Format::Plaintext,
+            _ => Format::Rle,
+        }
+    }
+}
+
+/// A finite pattern read from or written to a pattern file. Cell coordinates
+/// are relative to the pattern's top-left corner and only non-zero states are
+/// stored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pattern {
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<(i32, i32, u32)>,
+}
+
+impl Pattern {
+    /// Builds a pattern from absolute cell positions, shifting them so the
+    /// bounding box starts at the origin. Fails if the bounding box is more
+    /// than `i32::MAX` cells wide or tall.
+    pub fn from_cells(
+        cells: impl IntoIterator<Item = (i64, i64, u32)>,
+    ) -> Result<Self, PatternError> {
+        let cells = cells
+            .into_iter()
+            .filter(|&(_, _, state)| state > 0)
+            .collect::<Vec<_>>();
+        if cells.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let min_x = cells.iter().map(|c| c.0).min().unwrap();
+        let min_y = cells.iter().map(|c| c.1).min().unwrap();
+        let max_x = cells.iter().map(|c| c.0).max().unwrap();
+        let max_y = cells.iter().map(|c| c.1).max().unwrap();
+        let extent = |min: i64, max: i64| {
+            (max as i128 - min as i128 + 1)
+                .try_into()
+                .ok()
+                .filter(|&n: &u32| n <= i32::MAX as u32)
+        };
+        let (Some(width), Some(height)) = (extent(min_x, max_x), extent(min_y, max_y)) else {
+            return Err(PatternError::TooLarge);
+        };
+        let mut cells = cells
+            .into_iter()
+            .map(|(x, y, state)| ((x - min_x) as i32, (y - min_y) as i32, state))
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|&(x, y, _)| (y, x));
+
+        Ok(Self {
+            width,
+            height,
+            cells,
+            ..Default::default()
+        })
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// The cells grouped into rows from top to bottom, each sorted left to
+    /// right, as `(y, [(x, state)])`. Writers use this rather than a dense
+    /// grid so that sparse patterns stay cheap.
+    pub(crate) fn rows(&self) -> Vec<(i32, Vec<(i32, u32)>)> {
+        let mut cells = self
+            .cells
+            .iter()
+            .filter(|&&(_, _, state)| state > 0)
+            .copied()
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|&(x, y, _)| (y, x));
+        cells.dedup_by_key(|&mut (x, y, _)| (x, y));
+        let mut rows: Vec<(i32, Vec<(i32, u32)>)> = Vec::new();
+        for (x, y, state) in cells {
+            match rows.last_mut() {
+                Some((row, cells)) if *row == y => cells.push((x, state)),
+                _ => rows.push((y, vec![(x, state)])),
+            }
+        }
+        rows
+    }
+
+    pub fn rotate_clockwise(&self) -> Self {
+        let height = self.height as i32;
+        self.map_cells(self.height, self.width, |x, y| (height - 1 - y, x))
+    }
+
+    pub fn rotate_anticlockwise(&self) -> Self {
+        let width = self.width as i32;
+        self.map_cells(self.height, self.width, |x, y| (y, width - 1 - x))
+    }
+
+    /// Mirrors the pattern left to right.
+    pub fn flip_horizontal(&self) -> Self {
+        let width = self.width as i32;
+        self.map_cells(self.width, self.height, |x, y| (width - 1 - x, y))
+    }
+
+    /// Mirrors the pattern top to bottom.
+    pub fn flip_vertical(&self) -> Self {
+        let height = self.height as i32;
+        self.map_cells(self.width, self.height, |x, y| (x, height - 1 - y))
+    }
+
+    /// Moves every cell with `f`, keeping everything else.
+    fn map_cells(&self, width: u32, height: u32, f: impl Fn(i32, i32) -> (i32, i32)) -> Self {
+        let mut cells = self
+            .cells
+            .iter()
+            .map(|&(x, y, state)| {
+                let (x, y) = f(x, y);
+                (x, y, state)
+            })
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|&(x, y, _)| (y, x));
+        Self {
+            width,
+            height,
+            cells,
+            ..self.clone()
+        }
+    }
+
+    pub fn parse(text: &str, format: Format) -> Result<Self, PatternError> {
+        match format {
+            Format::Rle => rle::parse(text),
+            Format::Plaintext => plaintext::parse(text),
+            Format::Life105 => life::parse_105(text),
+            Format::Life106 => life::parse_106(text),
+        }
+    }
+
+    /// Writes the pattern in `format`. Life 1.05 and 1.06 only have live and
+    /// dead cells, so patterns with more states, or a Generations rule for
+    /// Life 1.05, are written as RLE instead.
+    pub fn write(&self, format: Format) -> String {
+        let multistate = self.cells.iter().any(|c| c.2 > 1);
+        let generations = self
+            .rule
+            .as_deref()
+            .and_then(|rule| rule.parse::<Rule>().ok())
+            .is_some_and(|rule| rule.states() > 2);
+        match format {
+            Format::Life105 | Format::Life106 if multistate => rle::write(self),
+            Format::Life105 if generations => rle::write(self),
+            Format::Rle => rle::write(self),
+            Format::Plaintext => plaintext::write(self),
+            Format::Life105 => life::write_105(self),
+            Format::Life106 => life::write_106(self),
+        }
+    }
+
+    /// Reads a pattern file in any supported format, detected from its contents.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PatternError> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text, Format::detect(&text))
+    }
+
+    /// Writes a pattern file in the format implied by its extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PatternError> {
+        let format = Format::from_path(path.as_ref());
+        fs::write(path, self.write(format))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(io::Error),
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// The cells span more than `i32::MAX` columns or rows.
+    TooLarge,
+}
+
+impl PatternError {
+    pub(crate) fn syntax(line: usize, message: impl Into<String>) -> Self {
+        PatternError::Syntax {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Io(e) => write!(f, "{}", e),
+            PatternError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            PatternError::TooLarge => write!(f, "pattern is too large"),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatternError::Io(e) => Some(e),
+            PatternError::Syntax { .. } | PatternError::TooLarge => None,
+        }
+    }
+}
+
+impl From<io::Error> for PatternError {
+    fn from(e: io::Error) -> Self {
+        PatternError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [Format; 4] = [
+        Format::Rle,
+        Format::Plaintext,
+        Format::Life105,
+        Format::Life106,
+    ];
+
+    /// A glider and a blinker with an empty row and column between them.
+    fn glider_and_blinker() -> Pattern {
+        let cells = [
+            (1, 0),
+            (2, 1),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (4, 4),
+            (5, 4),
+            (6, 4),
+        ];
+        Pattern {
+            name: Some("Glider and blinker".to_string()),
+            comments: vec!["Two objects".to_string()],
+            rule: Some("B3/S23".to_string()),
+            ..Pattern::from_cells(cells.map(|(x, y)| (x, y, 1))).unwrap()
+        }
+    }
+
+    fn sorted_cells(pattern: &Pattern) -> Vec<(i32, i32, u32)> {
+        let mut cells = pattern.cells.clone();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn round_trips_through_every_format() {
+        let pattern = glider_and_blinker();
+        for format in FORMATS {
+            let text = pattern.write(format);
+            assert_eq!(Format::detect(&text), format, "{}", text);
+            let parsed = Pattern::parse(&text, format).unwrap();
+            assert_eq!((parsed.width, parsed.height), (7, 5), "{:?}", format);
+            assert_eq!(
+                sorted_cells(&parsed),
+                sorted_cells(&pattern),
+                "{:?}",
+                format
+            );
+        }
+
+        let rle = Pattern::parse(&pattern.write(Format::Rle), Format::Rle).unwrap();
+        assert_eq!(rle.name, pattern.name);
+        assert_eq!(rle.comments, pattern.comments);
+        assert_eq!(rle.rule, pattern.rule);
+        let cells = Pattern::parse(&pattern.write(Format::Plaintext), Format::Plaintext).unwrap();
+        assert_eq!(cells.name, pattern.name);
+        assert_eq!(cells.comments, pattern.comments);
+    }
+
+    #[test]
+    fn moves_life_106_cells_to_the_origin() {
+        let pattern = Pattern::parse("#Life 1.06\n-1 -1\n1 1\n0 1\n", Format::Life106).unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(sorted_cells(&pattern), [(0, 0, 1), (1, 2, 1), (2, 2, 1)]);
+        assert_eq!(
+            pattern.write(Format::Life106),
+            "#Life 1.06\n0 0\n1 2\n2 2\n"
+        );
+    }
+
+    #[test]
+    fn rotates_and_flips() {
+        // X..
+        // XXB
+        let pattern = Pattern::from_cells([(0, 0, 1), (0, 1, 1), (1, 1, 1), (2, 1, 2)]).unwrap();
+        let clockwise = pattern.rotate_clockwise();
+        assert_eq!((clockwise.width, clockwise.height), (2, 3));
+        assert_eq!(
+            clockwise.cells,
+            [(0, 0, 1), (1, 0, 1), (0, 1, 1), (0, 2, 2)]
+        );
+        let anticlockwise = pattern.rotate_anticlockwise();
+        assert_eq!((anticlockwise.width, anticlockwise.height), (2, 3));
+        assert_eq!(
+            anticlockwise.cells,
+            [(1, 0, 2), (1, 1, 1), (0, 2, 1), (1, 2, 1)]
+        );
+        assert_eq!(
+            pattern.flip_horizontal().cells,
+            [(2, 0, 1), (0, 1, 2), (1, 1, 1), (2, 1, 1)]
+        );
+        assert_eq!(
+            pattern.flip_vertical().cells,
+            [(0, 0, 1), (1, 0, 1), (2, 0, 2), (0, 1, 1)]
+        );
+
+        let turned = (0..4).fold(pattern.clone(), |p, _| p.rotate_clockwise());
+        assert_eq!(turned, pattern);
+        assert_eq!(clockwise.rotate_anticlockwise(), pattern);
+        assert_eq!(pattern.flip_horizontal().flip_horizontal(), pattern);
+        assert_eq!(
+            pattern.rotate_clockwise().rotate_clockwise(),
+            pattern.flip_horizontal().flip_vertical()
+        );
+    }
+
+    #[test]
+    fn writes_multistate_life_patterns_as_rle() {
+        let pattern = Pattern::from_cells([(0, 0, 1), (1, 0, 2)]).unwrap();
+        for format in [Format::Life105, Format::Life106] {
+            let text = pattern.write(format);
+            assert_eq!(Format::detect(&text), Format::Rle);
+            assert_eq!(
+                Pattern::parse(&text, Format::Rle).unwrap().cells,
+                pattern.cells
+            );
+        }
+
+        let generations = Pattern {
+            rule: Some("B2/S/C3".to_string()),
+            ..Pattern::from_cells([(0, 0, 1)]).unwrap()
+        };
+        let text = generations.write(Format::Life105);
+        let parsed = Pattern::parse(&text, Format::detect(&text)).unwrap();
+        assert_eq!(parsed.rule.as_deref(), Some("B2/S/C3"));
+        assert!(generations
+            .write(Format::Life106)
+            .starts_with(life::HEADER_106));
+    }
+}
@@ -0,0 +1,59 @@
+use super::{Pattern, PatternError};
+
+/// Parses a plaintext `.cells` pattern: `!` comment lines followed by rows of
+/// `.` (dead) and `O` (alive).
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let mut y = 0;
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                'O' | 'o' | '*' => pattern.cells.push((x as i32, y, 1)),
+                c => {
+                    return Err(PatternError::syntax(
+                        line_index + 1,
+                        format!("unexpected character '{}', expected '.' or 'O'", c),
+                    ))
+                }
+            }
+        }
+        pattern.width = pattern.width.max(line.chars().count() as u32);
+        y += 1;
+    }
+    pattern.height = y as u32;
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("!Name: {}\n", name));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("!{}\n", comment));
+    }
+    let mut next_y = 0;
+    for (y, cells) in pattern.rows() {
+        out.extend((next_y..y).map(|_| '\n'));
+        next_y = y + 1;
+        let mut x = 0;
+        for (cell_x, _) in cells {
+            out.extend((x..cell_x).map(|_| '.'));
+            out.push('O');
+            x = cell_x + 1;
+        }
+        out.push('\n');
+    }
+    out.extend((next_y..pattern.height as i32).map(|_| '\n'));
+    out
+}
@@ -0,0 +1,308 @@
+use super::{Pattern, PatternError};
+
+const MAX_LINE_LENGTH: usize = 70;
+
+/// Parses a run-length encoded pattern, as used by Golly and LifeWiki.
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let mut header_seen = false;
+    let (mut x, mut y) = (0i64, 0i64);
+    let mut count: Option<u32> = None;
+    let mut prefix: Option<char> = None;
+    let mut finished = false;
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line_no = line_index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if !header_seen {
+            if let Some(comment) = line.strip_prefix('#') {
+                parse_comment(&mut pattern, comment);
+                continue;
+            }
+            parse_header(&mut pattern, line, line_no)?;
+            header_seen = true;
+            continue;
+        }
+
+        for c in line.chars() {
+            let run = count.unwrap_or(1) as i64;
+            // Runs may not go past the size given in the header, so a
+            // corrupt count can't make the pattern arbitrarily large.
+            let (width, height) = (pattern.width as i64, pattern.height as i64);
+            let past_width =
+                || PatternError::syntax(line_no, format!("run goes past the width of {}", width));
+            let state = match c {
+                'o' if prefix.is_none() => Some(1),
+                'A'..='X' => {
+                    let high = prefix.map_or(0, |p| p as u32 - 'p' as u32 + 1);
+                    Some(high * 24 + (c as u32 - 'A' as u32 + 1))
+                }
+                _ => None,
+            };
+            match (c, state) {
+                ('0'..='9', _) => {
+                    let digit = c.to_digit(10).unwrap();
+                    count = count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(digit));
+                    if count.is_none() {
+                        return Err(PatternError::syntax(line_no, "run count is too large"));
+                    }
+                    continue;
+                }
+                ('p'..='y', None) if prefix.is_none() => {
+                    prefix = Some(c);
+                    continue;
+                }
+                ('b' | '.', _) if prefix.is_none() => {
+                    x += run;
+                    if x > width {
+                        return Err(past_width());
+                    }
+                }
+                (_, Some(state)) => {
+                    if x + run > width {
+                        return Err(past_width());
+                    }
+                    if y >= height {
+                        return Err(PatternError::syntax(
+                            line_no,
+                            format!("cells go past the height of {}", height),
+                        ));
+                    }
+                    pattern
+                        .cells
+                        .extend((x..x + run).map(|x| (x as i32, y as i32, state)));
+                    x += run;
+                }
+                ('$', _) if prefix.is_none() => {
+                    // A trailing `$` may end on the row after the last.
+                    y = (y + run).min(height);
+                    x = 0;
+                }
+                ('!', _) if prefix.is_none() => {
+                    finished = true;
+                    break;
+                }
+                (c, _) if c.is_whitespace() && count.is_none() && prefix.is_none() => continue,
+                (c, _) => {
+                    return Err(PatternError::syntax(
+                        line_no,
+                        format!("unexpected character '{}' in pattern data", c),
+                    ))
+                }
+            }
+            count = None;
+            prefix = None;
+        }
+        if finished {
+            break;
+        }
+    }
+
+    if !header_seen {
+        return Err(PatternError::syntax(1, "missing 'x = .., y = ..' header"));
+    }
+
+    Ok(pattern)
+}
+
+fn parse_comment(pattern: &mut Pattern, comment: &str) {
+    let mut chars = comment.chars();
+    let kind = chars.next();
+    let text = chars.as_str().trim().to_string();
+    match kind {
+        Some('N') => pattern.name = Some(text),
+        Some('C') | Some('c') | Some('O') => pattern.comments.push(text),
+        _ => {}
+    }
+}
+
+fn parse_header(pattern: &mut Pattern, line: &str, line_no: usize) -> Result<(), PatternError> {
+    let mut width = None;
+    let mut height = None;
+    let mut rest = line;
+    while !rest.is_empty() {
+        let (field, tail) = rest.split_once(',').unwrap_or((rest, ""));
+        let (key, value) = field.split_once('=').ok_or_else(|| {
+            PatternError::syntax(
+                line_no,
+                format!("malformed header field '{}'", field.trim()),
+            )
+        })?;
+        if key.trim() == "rule" {
+            // Rules such as Larger than Life ones contain commas, so the
+            // rule is the rest of the line.
+            let (_, rule) = rest.split_once('=').unwrap_or_default();
+            pattern.rule = Some(rule.trim().to_string());
+            break;
+        }
+        let value = value.trim();
+        let dimension = || {
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|&n| n <= i32::MAX as u32)
+                .ok_or_else(|| {
+                    PatternError::syntax(line_no, format!("invalid dimension '{}'", value))
+                })
+        };
+        match key.trim() {
+            "x" => width = Some(dimension()?),
+            "y" => height = Some(dimension()?),
+            _ => {}
+        }
+        rest = tail;
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => {
+            pattern.width = width;
+            pattern.height = height;
+            Ok(())
+        }
+        _ => Err(PatternError::syntax(
+            line_no,
+            "expected a 'x = .., y = ..' header",
+        )),
+    }
+}
+
+/// Serialises a pattern in run-length encoded format.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#N {}\n", name));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("#C {}\n", comment));
+    }
+    out.push_str(&format!("x = {}, y = {}", pattern.width, pattern.height));
+    if let Some(rule) = &pattern.rule {
+        out.push_str(&format!(", rule = {}", rule));
+    }
+    out.push('\n');
+
+    let multistate = pattern.cells.iter().any(|c| c.2 > 1);
+    let mut tokens = Vec::new();
+    let mut last_y = 0;
+    for (y, cells) in pattern.rows() {
+        if y > last_y {
+            tokens.push(run_token((y - last_y) as usize, "$"));
+        }
+        last_y = y;
+
+        let mut x = 0;
+        let mut cells = cells.into_iter().peekable();
+        while let Some((start, state)) = cells.next() {
+            if start > x {
+                tokens.push(run_token((start - x) as usize, &state_tag(0, multistate)));
+            }
+            x = start + 1;
+            while cells
+                .next_if(|&(next, s)| next == x && s == state)
+                .is_some()
+            {
+                x += 1;
+            }
+            tokens.push(run_token(
+                (x - start) as usize,
+                &state_tag(state, multistate),
+            ));
+        }
+    }
+    tokens.push("!".to_string());
+
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len + token.len() > MAX_LINE_LENGTH {
+            out.push('\n');
+            line_len = 0;
+        }
+        line_len += token.len();
+        out.push_str(&token);
+    }
+    out.push('\n');
+    out
+}
+
+fn run_token(len: usize, tag: &str) -> String {
+    if len == 1 {
+        tag.to_string()
+    } else {
+        format!("{}{}", len, tag)
+    }
+}
+
+fn state_tag(state: u32, multistate: bool) -> String {
+    match (state, multistate) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (state, true) => {
+            let high = (state - 1) / 24;
+            let low = char::from(b'A' + ((state - 1) % 24) as u8);
+            if high == 0 {
+                low.to_string()
+            } else {
+                format!("{}{}", char::from(b'p' + (high - 1) as u8), low)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_spread_out_patterns_without_a_grid() {
+        let pattern =
+            Pattern::from_cells([(0, 0, 1), (1_000_000, 0, 1), (0, 1_000_000, 2)]).unwrap();
+        let text = write(&pattern);
+        assert!(text.ends_with("\nA999999.A1000000$B!\n"));
+        let parsed = parse(&text).unwrap();
+        assert_eq!(parsed.cells, pattern.cells);
+        assert_eq!((parsed.width, parsed.height), (1_000_001, 1_000_001));
+    }
+
+    #[test]
+    fn reads_rules_containing_commas() {
+        let rule = "R5,C0,M1,S34..58,B34..45,NM";
+        let pattern = Pattern {
+            rule: Some(rule.to_string()),
+            ..Pattern::from_cells([(0, 0, 1), (2, 1, 1)]).unwrap()
+        };
+        let parsed = parse(&write(&pattern)).unwrap();
+        assert_eq!(parsed.rule.as_deref(), Some(rule));
+        assert_eq!(parsed.cells, pattern.cells);
+
+        let parsed = parse("x = 3, y = 1, rule = R2,C0,M0,S2..3,B3..3,NM:T10,10\n3o!").unwrap();
+        assert_eq!(
+            parsed.rule.as_deref(),
+            Some("R2,C0,M0,S2..3,B3..3,NM:T10,10")
+        );
+    }
+
+    #[test]
+    fn rejects_runs_past_the_header() {
+        assert!(parse("x = 2, y = 2\n2000000000o!").is_err());
+        assert!(parse("x = 2, y = 2\n3o!").is_err());
+        assert!(parse("x = 2, y = 2\no2$o!").is_err());
+        assert!(parse("x = 2, y = 2\n2o$2o$!").is_ok());
+    }
+
+    #[test]
+    fn round_trips_multistate_cells() {
+        let cells = [(0, 0, 1), (1, 0, 2), (3, 0, 24), (0, 2, 25), (2, 2, 255)];
+        let pattern = Pattern::from_cells(cells).unwrap();
+        let text = write(&pattern);
+        assert!(text.ends_with("\nAB.X2$pA.yO!\n"), "{}", text);
+        assert_eq!(parse(&text).unwrap().cells, pattern.cells);
+    }
+}
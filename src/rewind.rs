@@ -0,0 +1,280 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use game_of_life::Engine;
+
+use crate::history::restore;
+
+/// Default memory for recorded generations.
+pub const DEFAULT_REWIND_MEMORY: usize = 64 * 1024 * 1024;
+/// Every this many recorded generations a full keyframe is stored instead of
+/// a delta.
+const KEYFRAME_INTERVAL: usize = 32;
+
+type Cells = Vec<(i64, i64, u32)>;
+const CELL_SIZE: usize = std::mem::size_of::<(i64, i64, u32)>();
+
+enum FrameData {
+    /// Every live cell, sorted by row.
+    Keyframe(Cells),
+    /// Cells that changed since the previous frame, with their new state.
+    Delta(Cells),
+}
+
+struct Frame {
+    generation: u64,
+    data: FrameData,
+}
+
+impl Frame {
+    fn keyframe(generation: u64, cells: &[(i64, i64, u32)]) -> Self {
+        Self {
+            generation,
+            data: FrameData::Keyframe(cells.to_vec()),
+        }
+    }
+
+    fn is_keyframe(&self) -> bool {
+        matches!(self.data, FrameData::Keyframe(_))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + match &self.data {
+                FrameData::Keyframe(cells) | FrameData::Delta(cells) => cells.len() * CELL_SIZE,
+            }
+    }
+}
+
+/// Rebuilds the cells after a keyframe and the deltas that follow it.
+fn replay<'a>(frames: impl IntoIterator<Item = &'a Frame>) -> Cells {
+    // Keyed by `(y, x)` so the result comes out sorted by row.
+    let mut cells = BTreeMap::new();
+    for frame in frames {
+        apply(frame, &mut cells);
+    }
+    cells
+        .into_iter()
+        .map(|((y, x), state)| (x, y, state))
+        .collect()
+}
+
+fn apply(frame: &Frame, cells: &mut BTreeMap<(i64, i64), u32>) {
+    match &frame.data {
+        FrameData::Keyframe(keyframe) => {
+            cells.clear();
+            cells.extend(keyframe.iter().map(|&(x, y, state)| ((y, x), state)));
+        }
+        FrameData::Delta(changes) => {
+            for &(x, y, state) in changes {
+                if state > 0 {
+                    cells.insert((y, x), state);
+                } else {
+                    cells.remove(&(y, x));
+                }
+            }
+        }
+    }
+}
+
+/// Live cells sorted by row, then column.
+fn sorted_cells(universe: &dyn Engine) -> Cells {
+    let mut cells = universe.live_cells();
+    cells.sort_unstable_by_key(|&(x, y, _)| (y, x));
+    cells
+}
+
+/// The changes that turn `old` into `new`. Both must be sorted by row.
+fn diff(old: &[(i64, i64, u32)], new: &[(i64, i64, u32)]) -> Cells {
+    let key = |&(x, y, _): &(i64, i64, u32)| (y, x);
+    let (mut old, mut new) = (old.iter().peekable(), new.iter().peekable());
+    let mut changes = Vec::new();
+    loop {
+        match (old.peek(), new.peek()) {
+            (Some(&&o), Some(&&n)) if key(&o) == key(&n) => {
+                if o.2 != n.2 {
+                    changes.push(n);
+                }
+                old.next();
+                new.next();
+            }
+            (Some(&&o), Some(&&n)) if key(&o) < key(&n) => {
+                changes.push((o.0, o.1, 0));
+                old.next();
+            }
+            (Some(&&o), None) => {
+                changes.push((o.0, o.1, 0));
+                old.next();
+            }
+            (_, Some(&&n)) => {
+                changes.push(n);
+                new.next();
+            }
+            (None, None) => return changes,
+        }
+    }
+}
+
+/// A ring buffer of recent generations stored as periodic keyframes and the
+/// deltas between them. The oldest keyframe and its deltas are dropped when
+/// the memory limit is exceeded. Generation 0 is kept separately so it can
+/// always be returned to.
+pub struct Rewind {
+    frames: VecDeque<Frame>,
+    /// Cells of the newest frame, if it can be diffed against.
+    last: Option<Cells>,
+    since_keyframe: usize,
+    start: Option<Frame>,
+    used: usize,
+    memory_limit: usize,
+}
+
+impl Rewind {
+    pub fn new(memory_limit: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            last: None,
+            since_keyframe: 0,
+            start: None,
+            used: 0,
+            memory_limit,
+        }
+    }
+
+    /// Records the universe's current generation. Frames at or after it
+    /// belong to a future that is being replaced and are discarded. A
+    /// generation too large to fit in memory clears the recording instead,
+    /// without reading out its cells.
+    pub fn record(&mut self, universe: &dyn Engine) {
+        let generation = universe.generation();
+        while self
+            .frames
+            .back()
+            .is_some_and(|frame| frame.generation >= generation)
+        {
+            let frame = self.frames.pop_back().unwrap();
+            self.used -= frame.size();
+            self.last = None;
+        }
+
+        let population = usize::try_from(universe.population()).unwrap_or(usize::MAX);
+        if population.saturating_mul(CELL_SIZE) > self.memory_limit {
+            self.frames.clear();
+            self.used = 0;
+            self.last = None;
+            return;
+        }
+
+        let cells = sorted_cells(universe);
+        if generation == 0 {
+            self.start = Some(Frame::keyframe(0, &cells));
+        }
+        let frame = match &self.last {
+            Some(last) if self.since_keyframe < KEYFRAME_INTERVAL => {
+                self.since_keyframe += 1;
+                Frame {
+                    generation,
+                    data: FrameData::Delta(diff(last, &cells)),
+                }
+            }
+            _ => {
+                self.since_keyframe = 1;
+                Frame::keyframe(generation, &cells)
+            }
+        };
+        self.used += frame.size();
+        self.frames.push_back(frame);
+        self.last = Some(cells);
+        self.evict();
+    }
+
+    /// Moves `offset` recorded generations away from the current one and
+    /// returns the generation reached, or `None` if there is nowhere to go.
+    pub fn seek(&mut self, universe: &mut dyn Engine, offset: isize) -> Option<u64> {
+        let generation = universe.generation();
+        if self
+            .frames
+            .back()
+            .is_none_or(|frame| frame.generation < generation)
+        {
+            self.record(universe);
+        }
+        let current = self
+            .frames
+            .iter()
+            .position(|frame| frame.generation == generation)?;
+        let target = current
+            .saturating_add_signed(offset)
+            .min(self.frames.len() - 1);
+        if target == current {
+            return None;
+        }
+
+        let keyframe = self.frames.range(..=target).rposition(Frame::is_keyframe)?;
+        let cells = replay(self.frames.range(keyframe..=target));
+        let generation = self.frames[target].generation;
+        restore(universe, &cells, generation);
+        Some(generation)
+    }
+
+    /// Restores the last state recorded at generation 0.
+    pub fn rewind_to_start(&self, universe: &mut dyn Engine) -> bool {
+        let Some(start) = &self.start else {
+            return false;
+        };
+        restore(universe, &replay([start]), 0);
+        true
+    }
+
+    /// Drops whole keyframe groups from the front, always keeping the newest.
+    fn evict(&mut self) {
+        while self.used > self.memory_limit {
+            let Some(next_keyframe) = self.frames.iter().skip(1).position(Frame::is_keyframe)
+            else {
+                break;
+            };
+            for frame in self.frames.drain(..=next_keyframe) {
+                self.used -= frame.size();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game_of_life::{Backend, Topology};
+
+    use super::*;
+
+    #[test]
+    fn seeks_back_through_keyframes_and_deltas() {
+        let mut universe = Backend::HashLife.create(0, 0, Topology::Plane).unwrap();
+        let far = 1 << 40;
+        for (x, y) in [(0, 0), (1, 0), (2, 0), (far, -far), (far, 1 - far)] {
+            universe.set(x, y, 1);
+        }
+        let mut rewind = Rewind::new(DEFAULT_REWIND_MEMORY);
+        let mut history = Vec::new();
+        for _ in 0..KEYFRAME_INTERVAL * 2 + 5 {
+            history.push(sorted_cells(universe.as_ref()));
+            rewind.record(universe.as_ref());
+            universe.step();
+        }
+        let generations = history.len() as u64;
+        assert_eq!(rewind.seek(universe.as_mut(), -40), Some(generations - 40));
+        assert_eq!(sorted_cells(universe.as_ref()), history[history.len() - 40]);
+        assert!(rewind.rewind_to_start(universe.as_mut()));
+        assert_eq!(sorted_cells(universe.as_ref()), history[0]);
+    }
+
+    #[test]
+    fn skips_generations_larger_than_the_limit() {
+        let mut universe = Backend::Sparse.create(0, 0, Topology::Plane).unwrap();
+        for x in 0..10 {
+            universe.set(x, 0, 1);
+        }
+        let mut rewind = Rewind::new(5 * CELL_SIZE);
+        rewind.record(universe.as_ref());
+        assert!(rewind.frames.is_empty());
+        assert!(!rewind.rewind_to_start(universe.as_mut()));
+    }
+}
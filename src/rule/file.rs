@@ -0,0 +1,184 @@
+use std::{fmt, fs, io, path::Path, sync::Arc};
+
+use super::{table, tree, Rule};
+
+/// Numbered lines of a section.
+type Lines<'a> = Vec<(usize, &'a str)>;
+
+/// A rule loaded from a Golly `.rule` file, with its `@TABLE` or `@TREE`
+/// compiled into a [`Rule`].
+#[derive(Clone, Debug)]
+pub struct RuleFile {
+    pub rule: Rule,
+    /// Display colours by state from the `@COLORS` section.
+    pub colours: Vec<(u32, [u8; 3])>,
+}
+
+impl RuleFile {
+    /// Parses the text of a `.rule` file. Sections other than `@RULE`,
+    /// `@TABLE`, `@TREE` and `@COLORS` are ignored.
+    pub fn parse(text: &str) -> Result<Self, RuleFileError> {
+        // Each section's header line, name and numbered body lines.
+        let mut sections: Vec<(usize, &str, Lines)> = Vec::new();
+        let mut name = None;
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            match (line.trim().strip_prefix('@'), sections.last_mut()) {
+                (Some(header), _) => {
+                    let (section, argument) = header.split_once(' ').unwrap_or((header, ""));
+                    if section == "RULE" {
+                        name = Some(argument.trim().to_string());
+                    }
+                    sections.push((number, section, Vec::new()));
+                }
+                (None, Some((_, _, lines))) => lines.push((number, line)),
+                (None, None) => {}
+            }
+        }
+        let name = name
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| RuleFileError::syntax(1, "missing @RULE name"))?;
+
+        let mut tree = None;
+        let mut colours = Vec::new();
+        for (header, section, lines) in sections {
+            match section {
+                "TABLE" if tree.is_none() => {
+                    tree = Some(table::parse(&name, header, lines)?);
+                }
+                "TREE" if tree.is_none() => tree = Some(tree::parse(&name, header, lines)?),
+                "COLORS" => colours = lines,
+                _ => {}
+            }
+        }
+        let tree = tree.ok_or_else(|| RuleFileError::syntax(1, "missing @TABLE or @TREE"))?;
+        Ok(Self {
+            colours: parse_colours(colours, tree.states())?,
+            rule: Rule::from_tree(Arc::new(tree)),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RuleFileError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+/// Reads `state r g b` lines, or `r g b r g b` lines giving a gradient
+/// across the live states of a rule with `states` states.
+fn parse_colours<'a>(
+    lines: impl IntoIterator<Item = (usize, &'a str)>,
+    states: u32,
+) -> Result<Vec<(u32, [u8; 3])>, RuleFileError> {
+    let mut colours = Vec::new();
+    for (line, text) in lines {
+        let text = text.split('#').next().unwrap_or("");
+        let numbers = text
+            .split_whitespace()
+            .map(str::parse::<u32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                RuleFileError::syntax(line, format!("invalid colour '{}'", text.trim()))
+            })?;
+        let rgb = |r: u32, g: u32, b: u32| [r, g, b].map(|c| c.min(255) as u8);
+        match *numbers.as_slice() {
+            [] => {}
+            [state, r, g, b] if state < states => colours.push((state, rgb(r, g, b))),
+            [_, _, _, _] => {}
+            [r0, g0, b0, r1, g1, b1] => {
+                let (from, to) = (rgb(r0, g0, b0), rgb(r1, g1, b1));
+                for state in 1..states {
+                    let t = (state - 1) as f32 / (states - 2).max(1) as f32;
+                    let mix =
+                        |i: usize| (from[i] as f32 + (to[i] as f32 - from[i] as f32) * t) as u8;
+                    colours.push((state, [mix(0), mix(1), mix(2)]));
+                }
+            }
+            _ => return Err(RuleFileError::syntax(line, "expected 'state r g b'")),
+        }
+    }
+    Ok(colours)
+}
+
+#[derive(Debug)]
+pub enum RuleFileError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl RuleFileError {
+    pub(crate) fn syntax(line: usize, message: impl Into<String>) -> Self {
+        RuleFileError::Syntax {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for RuleFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleFileError::Io(e) => write!(f, "{}", e),
+            RuleFileError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for RuleFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RuleFileError::Io(e) => Some(e),
+            RuleFileError::Syntax { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for RuleFileError {
+    fn from(e: io::Error) -> Self {
+        RuleFileError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TREE: &str = "\
+@RULE North
+@TREE
+num_states=4
+num_neighbors=4
+1 0 0 0 0
+2 0 0 0 0
+3 1 1 1 1
+4 2 2 2 2
+5 3 3 3 3
+";
+
+    #[test]
+    fn reads_colours_and_gradients() {
+        let file = RuleFile::parse(&format!("{TREE}@COLORS\n0 0 0 255 255 255\n")).unwrap();
+        assert_eq!(file.rule.states(), 4);
+        assert_eq!(
+            file.colours,
+            [(1, [0, 0, 0]), (2, [127, 127, 127]), (3, [255, 255, 255])]
+        );
+
+        let text = format!("{TREE}@COLORS\n# comment\n2 300 0 10\n7 1 2 3\n\n");
+        let file = RuleFile::parse(&text).unwrap();
+        assert_eq!(file.colours, [(2, [255, 0, 10])]);
+
+        let text = format!("{TREE}@COLORS\n1 2 3\n");
+        assert!(matches!(
+            RuleFile::parse(&text),
+            Err(RuleFileError::Syntax { line: 11, .. })
+        ));
+    }
+
+    #[test]
+    fn needs_a_name_and_a_table_or_tree() {
+        assert!(RuleFile::parse("@RULE\n@TREE\n").is_err());
+        assert!(RuleFile::parse("@RULE Empty\n@COLORS\n1 255 0 0\n").is_err());
+        let file = RuleFile::parse(&format!("# intro\n{TREE}@ICONS\nanything\n")).unwrap();
+        assert_eq!(file.rule.tree().unwrap().name, "North");
+    }
+}
@@ -0,0 +1,395 @@
+use std::{fmt, ops::RangeInclusive};
+
+use super::{tree::MAX_STATES, ParseRuleError};
+
+/// The largest supported neighbourhood range.
+pub const MAX_RANGE: u32 = 500;
+
+/// The shape of a range-R neighbourhood.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// The whole (2R+1)x(2R+1) square.
+    Moore,
+    /// Cells within R steps along the axes.
+    VonNeumann,
+    /// Cells within R + 1/2 of the centre.
+    Circular,
+    /// The row and column through the centre.
+    Cross,
+    /// The rows and columns either side of the centre.
+    Hash,
+    /// One weight per cell of the (2R+1)x(2R+1) square, row by row.
+    Weighted(Vec<u32>),
+}
+
+/// A rectangle of neighbourhood offsets that all have the same weight, with
+/// inclusive bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+    pub weight: u32,
+}
+
+/// A Larger than Life (HROT) rule: birth and survival depend on the weighted
+/// count of live cells in a neighbourhood of range `range`, written as in
+/// `R5,C0,M1,S34..58,B34..45,NM`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeRule {
+    pub range: u32,
+    pub neighbourhood: Neighbourhood,
+    /// Whether a cell counts itself. A weighted neighbourhood with no weight
+    /// on the centre gives it a weight of 1.
+    pub middle: bool,
+    pub birth: Vec<RangeInclusive<u32>>,
+    pub survival: Vec<RangeInclusive<u32>>,
+}
+
+impl RangeRule {
+    /// The weight of the cell at offset `(dx, dy)`, which must be within range.
+    pub fn weight(&self, dx: i32, dy: i32) -> u32 {
+        if (dx, dy) == (0, 0) && !self.middle {
+            return 0;
+        }
+        let r = self.range as i32;
+        let inside = match &self.neighbourhood {
+            Neighbourhood::Moore => true,
+            Neighbourhood::VonNeumann => dx.abs() + dy.abs() <= r,
+            Neighbourhood::Circular => dx * dx + dy * dy <= r * r + r,
+            Neighbourhood::Cross => dx == 0 || dy == 0,
+            Neighbourhood::Hash => dx.abs() == 1 || dy.abs() == 1,
+            Neighbourhood::Weighted(weights) => {
+                let side = 2 * r + 1;
+                return match weights[((dy + r) * side + dx + r) as usize] {
+                    0 if (dx, dy) == (0, 0) => 1,
+                    weight => weight,
+                };
+            }
+        };
+        inside as u32
+    }
+
+    /// The neighbourhood split into rectangles of equal weight, so that it
+    /// can be counted with a few lookups in a summed-area table.
+    pub fn blocks(&self) -> Vec<Block> {
+        let r = self.range as i32;
+        let mut blocks: Vec<Block> = Vec::new();
+        for dy in -r..=r {
+            let mut dx = -r;
+            while dx <= r {
+                let weight = self.weight(dx, dy);
+                let x0 = dx;
+                while dx <= r && self.weight(dx, dy) == weight {
+                    dx += 1;
+                }
+                if weight == 0 {
+                    continue;
+                }
+                let run = Block {
+                    x0,
+                    y0: dy,
+                    x1: dx - 1,
+                    y1: dy,
+                    weight,
+                };
+                // Runs matching one in the row above extend it downwards.
+                match blocks.iter_mut().find(|block| {
+                    block.y1 == dy - 1
+                        && (block.x0, block.x1, block.weight) == (run.x0, run.x1, run.weight)
+                }) {
+                    Some(block) => block.y1 = dy,
+                    None => blocks.push(run),
+                }
+            }
+        }
+        blocks
+    }
+
+    pub fn birth(&self, count: u32) -> bool {
+        self.birth.iter().any(|range| range.contains(&count))
+    }
+
+    pub fn survival(&self, count: u32) -> bool {
+        self.survival.iter().any(|range| range.contains(&count))
+    }
+
+    /// The equivalent birth and survival tables by number of neighbours, if
+    /// this is a plain range-1 Moore rule.
+    pub(super) fn to_moore(&self) -> Option<([bool; 9], [bool; 9])> {
+        if self.range != 1 || self.neighbourhood != Neighbourhood::Moore {
+            return None;
+        }
+        let mut birth = [false; 9];
+        let mut survival = [false; 9];
+        for n in 0..9 {
+            birth[n as usize] = self.birth(n);
+            survival[n as usize] = self.survival(n + self.middle as u32);
+        }
+        Some((birth, survival))
+    }
+}
+
+fn parse_counts(counts: &str) -> Option<RangeInclusive<u32>> {
+    match counts.split_once("..") {
+        Some((min, max)) => Some(min.parse().ok()?..=max.parse().ok()?),
+        None => {
+            let count = counts.parse().ok()?;
+            Some(count..=count)
+        }
+    }
+}
+
+fn parse_neighbourhood(neighbourhood: &str, range: u32) -> Option<Neighbourhood> {
+    let cells = ((2 * range + 1) * (2 * range + 1)) as usize;
+    let hex_digits = || neighbourhood[1..].chars().map(|c| c.to_digit(16));
+    match neighbourhood {
+        "m" => Some(Neighbourhood::Moore),
+        "n" => Some(Neighbourhood::VonNeumann),
+        "c" => Some(Neighbourhood::Circular),
+        "+" => Some(Neighbourhood::Cross),
+        "#" => Some(Neighbourhood::Hash),
+        // One bit per cell, most significant first, padded to whole digits.
+        _ if neighbourhood.starts_with('@') && neighbourhood.len() == 1 + cells.div_ceil(4) => {
+            let bits = hex_digits()
+                .flat_map(|digit| (0..4).rev().map(move |bit| digit.map(|d| d >> bit & 1)))
+                .take(cells)
+                .collect::<Option<Vec<_>>>()?;
+            Some(Neighbourhood::Weighted(bits))
+        }
+        _ if neighbourhood.starts_with('w') && neighbourhood.len() == 1 + cells => Some(
+            Neighbourhood::Weighted(hex_digits().collect::<Option<_>>()?),
+        ),
+        _ => None,
+    }
+}
+
+/// Parses a lowercased HROT rule string into the rule and its state count.
+pub(super) fn parse(s: &str) -> Result<(RangeRule, u32), ParseRuleError> {
+    let invalid = |part: &str| ParseRuleError::InvalidRangeRule(part.to_string());
+    let mut range = None;
+    let mut states = 2;
+    let mut middle = false;
+    let mut neighbourhood = "m";
+    let mut birth = None;
+    let mut survival = None;
+    // Bare counts continue the birth or survival list before them.
+    let mut list = None;
+    for part in s.split(',') {
+        let (key, value) = match part.chars().next() {
+            Some(c) if c.is_ascii_digit() => (list.ok_or_else(|| invalid(part))?, part),
+            Some(c) => (c, &part[c.len_utf8()..]),
+            None => return Err(invalid(part)),
+        };
+        list = None;
+        match key {
+            'r' => {
+                range = value
+                    .parse()
+                    .ok()
+                    .filter(|range| (1..=MAX_RANGE).contains(range))
+                    .map(Some)
+                    .ok_or_else(|| invalid(part))?
+            }
+            'c' => {
+                states = match value.parse() {
+                    Ok(0) => 2,
+                    Ok(n) if (2..=MAX_STATES).contains(&n) => n,
+                    _ => return Err(ParseRuleError::InvalidStates(value.to_string())),
+                }
+            }
+            'm' => {
+                middle = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(invalid(part)),
+                }
+            }
+            'b' | 's' => {
+                let counts = if key == 'b' {
+                    &mut birth
+                } else {
+                    &mut survival
+                };
+                let counts = counts.get_or_insert_with(Vec::new);
+                if !value.is_empty() {
+                    counts.push(parse_counts(value).ok_or_else(|| invalid(part))?);
+                }
+                list = Some(key);
+            }
+            'n' => neighbourhood = value,
+            _ => return Err(invalid(part)),
+        }
+    }
+
+    let (Some(range), Some(birth), Some(survival)) = (range, birth, survival) else {
+        return Err(invalid(s));
+    };
+    let rule = RangeRule {
+        range,
+        neighbourhood: parse_neighbourhood(neighbourhood, range)
+            .ok_or_else(|| invalid(&format!("n{}", neighbourhood)))?,
+        middle,
+        birth,
+        survival,
+    };
+    Ok((rule, states))
+}
+
+/// Writes the rule in HROT notation with the given number of states.
+pub(super) fn write(rule: &RangeRule, states: u32, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let counts = |counts: &[RangeInclusive<u32>]| {
+        counts
+            .iter()
+            .map(|range| {
+                if range.start() == range.end() {
+                    range.start().to_string()
+                } else {
+                    format!("{}..{}", range.start(), range.end())
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    write!(
+        f,
+        "R{},C{},M{},S{},B{},N",
+        rule.range,
+        if states == 2 { 0 } else { states },
+        rule.middle as u32,
+        counts(&rule.survival),
+        counts(&rule.birth),
+    )?;
+    match &rule.neighbourhood {
+        Neighbourhood::Moore => write!(f, "M"),
+        Neighbourhood::VonNeumann => write!(f, "N"),
+        Neighbourhood::Circular => write!(f, "C"),
+        Neighbourhood::Cross => write!(f, "+"),
+        Neighbourhood::Hash => write!(f, "#"),
+        Neighbourhood::Weighted(weights) if weights.iter().all(|&w| w <= 1) => {
+            write!(f, "@")?;
+            for digit in weights.chunks(4) {
+                let digit = (0..4).fold(0, |d, i| d << 1 | digit.get(i).copied().unwrap_or(0));
+                write!(f, "{:X}", digit)?;
+            }
+            Ok(())
+        }
+        Neighbourhood::Weighted(weights) => {
+            write!(f, "W")?;
+            weights.iter().try_for_each(|w| write!(f, "{:X}", w))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+
+    fn range_rule(s: &str) -> RangeRule {
+        parse(&s.to_ascii_lowercase()).unwrap().0
+    }
+
+    #[test]
+    fn parses_bosco() {
+        let (rule, states) = parse("r5,c0,m1,s34..58,b34..45,nm").unwrap();
+        assert_eq!(states, 2);
+        assert_eq!(
+            rule,
+            RangeRule {
+                range: 5,
+                neighbourhood: Neighbourhood::Moore,
+                middle: true,
+                birth: vec![34..=45],
+                survival: vec![34..=58],
+            }
+        );
+        assert!(rule.birth(34) && rule.birth(45) && !rule.birth(46));
+    }
+
+    #[test]
+    fn round_trips_rule_strings() {
+        let rules = [
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R3,C4,M0,S2,4..6,B3,7..9,NN",
+            "R2,C0,M0,S1..3,B2,NC",
+            "R2,C0,M1,S2,B3,N+",
+            "R2,C0,M0,S,B3,N#",
+            "R1,C0,M0,S1,B2,N@550",
+            "R1,C0,M0,S2..3,B3,NW121202121",
+        ];
+        for rule in rules {
+            assert_eq!(rule.parse::<Rule>().unwrap().to_string(), rule);
+        }
+        // A plain range-1 Moore rule is an ordinary Life-like rule.
+        assert_eq!("R1,C0,M0,S2..3,B3,NM".parse::<Rule>(), Ok(Rule::LIFE));
+        assert_eq!("R1,C0,M1,S3..4,B3,NM".parse::<Rule>(), Ok(Rule::LIFE));
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        for rule in [
+            "r0,c0,m0,s2,b3,nm",
+            "r501,c0,m0,s2,b3,nm",
+            "r2,c0,m2,s2,b3,nm",
+            "r2,c0,m0,s2,b3,nx",
+            "r2,c0,m0,b3,nm",
+            "r2,c0,m0,s2..,b3,nm",
+            "r1,c0,m0,s2,b3,n@55",
+            "r1,c0,m0,s2,b3,nw12120212",
+        ] {
+            assert!(parse(rule).is_err(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn neighbourhoods_have_the_right_cells() {
+        let sizes = [("m", 24), ("n", 12), ("c", 20), ("+", 8), ("#", 16)];
+        for (neighbourhood, size) in sizes {
+            let rule = range_rule(&format!("r2,c0,m0,s2,b3,n{}", neighbourhood));
+            let cells = (-2..=2)
+                .flat_map(|dy| (-2..=2).map(move |dx| (dx, dy)))
+                .map(|(dx, dy)| rule.weight(dx, dy))
+                .sum::<u32>();
+            assert_eq!(cells, size, "{}", neighbourhood);
+            let middle = range_rule(&format!("r2,c0,m1,s2,b3,n{}", neighbourhood));
+            assert_eq!(middle.weight(0, 0), (neighbourhood != "#") as u32);
+        }
+        // Weighted neighbourhoods count the centre once unless it has a weight.
+        let weighted = range_rule("r1,c0,m1,s2,b3,nw121202121");
+        assert_eq!((weighted.weight(-1, -1), weighted.weight(0, -1)), (1, 2));
+        assert_eq!(weighted.weight(0, 0), 1);
+    }
+
+    #[test]
+    fn blocks_cover_each_weight_once() {
+        let rules = [
+            "r3,c0,m1,s2,b3,nm",
+            "r3,c0,m0,s2,b3,nn",
+            "r4,c0,m1,s2,b3,nc",
+            "r3,c0,m0,s2,b3,n+",
+            "r3,c0,m1,s2,b3,n#",
+            "r1,c0,m0,s2,b3,nw3210f0123",
+        ];
+        for rule in rules {
+            let rule = range_rule(rule);
+            let r = rule.range as i32;
+            let side = 2 * r + 1;
+            let mut weights = vec![0; (side * side) as usize];
+            for block in rule.blocks() {
+                for dy in block.y0..=block.y1 {
+                    for dx in block.x0..=block.x1 {
+                        weights[((dy + r) * side + dx + r) as usize] += block.weight;
+                    }
+                }
+            }
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let weight = weights[((dy + r) * side + dx + r) as usize];
+                    assert_eq!(weight, rule.weight(dx, dy), "{:?} at {dx},{dy}", rule);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,708 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+pub mod file;
+pub mod hrot;
+mod table;
+pub mod tree;
+
+use hrot::RangeRule;
+use tree::{RuleTree, MAX_STATES};
+
+/// A birth/survival rule.
+///
+/// Outer-totalistic rules are stored as lookup tables indexed by the number
+/// of live neighbours (0-8). Isotropic non-totalistic rules, written in Hensel
+/// notation as in `B2-a/S12`, also carry a 512-entry table indexed by the
+/// whole 3x3 neighbourhood (see [`neighbourhood_bit`]). Larger than Life
+/// rules count over a wider neighbourhood and are described by a
+/// [`RangeRule`]. Rules loaded from Golly `.rule` files are evaluated
+/// through a [`RuleTree`].
+///
+/// With more than two states it is a Generations rule: state 1 is alive and
+/// is the only state counted as a neighbour, and a live cell that does not
+/// survive decays through states 2, 3, ... before dying.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    birth: [bool; 9],
+    survival: [bool; 9],
+    states: u32,
+    /// One bit per 3x3 neighbourhood, set if the centre is alive next
+    /// generation. `None` for outer-totalistic rules.
+    neighbourhoods: Option<[u64; 8]>,
+    range: Option<Box<RangeRule>>,
+    tree: Option<Arc<RuleTree>>,
+}
+
+/// Well-known rules, as (name, rule string) pairs.
+pub const PRESETS: [(&str, &str); 13] = [
+    ("Life", "B3/S23"),
+    ("HighLife", "B36/S23"),
+    ("Day & Night", "B3678/S34678"),
+    ("Seeds", "B2/S"),
+    ("Maze", "B3/S12345"),
+    ("Life without Death", "B3/S012345678"),
+    ("2x2", "B36/S125"),
+    ("Replicator", "B1357/S1357"),
+    ("Brian's Brain", "B2/S/C3"),
+    ("Star Wars", "B2/S345/C4"),
+    ("tlife", "B3/S2-i34q"),
+    ("Just Friends", "B2-a/S12"),
+    ("Bosco's Rule", "R5,C0,M1,S34..58,B34..45,NM"),
+];
+
+/// The centre cell's bit in a 3x3 neighbourhood index.
+const CENTRE: u32 = 1 << 4;
+/// Every bit of a 3x3 neighbourhood index except the centre.
+const NEIGHBOURS: u32 = 0x1ef;
+
+/// Hensel letters for 0 to 4 live neighbours, in canonical order, each with
+/// one neighbourhood it stands for. Counts 5 to 8 use the complements of
+/// counts 3 to 0.
+const HENSEL: [&[(char, u32)]; 5] = [
+    &[],
+    &[('c', 1), ('e', 2)],
+    &[
+        ('c', 5),
+        ('e', 10),
+        ('k', 33),
+        ('a', 3),
+        ('i', 40),
+        ('n', 68),
+    ],
+    &[
+        ('c', 69),
+        ('e', 42),
+        ('k', 98),
+        ('a', 11),
+        ('i', 7),
+        ('n', 13),
+        ('y', 97),
+        ('q', 70),
+        ('j', 14),
+        ('r', 41),
+    ],
+    &[
+        ('c', 325),
+        ('e', 170),
+        ('k', 99),
+        ('a', 15),
+        ('i', 45),
+        ('n', 71),
+        ('y', 101),
+        ('q', 102),
+        ('j', 106),
+        ('r', 43),
+        ('t', 105),
+        ('w', 78),
+        ('z', 108),
+    ],
+];
+
+/// Every letter used in Hensel notation.
+const HENSEL_LETTERS: &str = "cekainyqjrtwz";
+
+/// The bit of the neighbour at offset `(dx, dy)` in a 3x3 neighbourhood
+/// index. Bits run row by row from the top-left, so the centre is bit 4.
+pub const fn neighbourhood_bit(dx: i32, dy: i32) -> u32 {
+    1 << ((dy + 1) * 3 + dx + 1)
+}
+
+/// The Hensel letters for `count` live neighbours with their neighbourhoods.
+fn hensel_letters(count: u32) -> impl Iterator<Item = (char, u32)> {
+    let (letters, complement) = match count {
+        0..=4 => (HENSEL[count as usize], 0),
+        _ => (HENSEL[8 - count as usize], NEIGHBOURS),
+    };
+    letters
+        .iter()
+        .map(move |&(letter, neighbourhood)| (letter, neighbourhood ^ complement))
+}
+
+/// The neighbourhoods `neighbourhood` turns into under rotation and
+/// reflection.
+fn symmetries(neighbourhood: u32) -> impl Iterator<Item = u32> {
+    // Each symmetry is a choice of whether to swap the axes and whether to
+    // mirror each of them.
+    (0..8).map(move |symmetry| {
+        let mut result = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if neighbourhood & neighbourhood_bit(dx, dy) != 0 {
+                    let (x, y) = if symmetry & 4 != 0 {
+                        (dy, dx)
+                    } else {
+                        (dx, dy)
+                    };
+                    let x = if symmetry & 1 != 0 { -x } else { x };
+                    let y = if symmetry & 2 != 0 { -y } else { y };
+                    result |= neighbourhood_bit(x, y);
+                }
+            }
+        }
+        result
+    })
+}
+
+fn get_bit(table: &[u64; 8], index: u32) -> bool {
+    table[index as usize / 64] >> (index % 64) & 1 == 1
+}
+
+fn set_bit(table: &mut [u64; 8], index: u32) {
+    table[index as usize / 64] |= 1 << (index % 64);
+}
+
+impl Rule {
+    pub const LIFE: Rule = Rule {
+        birth: [false, false, false, true, false, false, false, false, false],
+        survival: [false, false, true, true, false, false, false, false, false],
+        states: 2,
+        neighbourhoods: None,
+        range: None,
+        tree: None,
+    };
+
+    pub fn new(birth: [bool; 9], survival: [bool; 9]) -> Self {
+        Self {
+            birth,
+            survival,
+            states: 2,
+            neighbourhoods: None,
+            range: None,
+            tree: None,
+        }
+    }
+
+    /// A rule that follows `tree`, with as many states as it has.
+    pub fn from_tree(tree: Arc<RuleTree>) -> Self {
+        Self {
+            states: tree.states(),
+            tree: Some(tree),
+            ..Self::new([false; 9], [false; 9])
+        }
+    }
+
+    /// An isotropic non-totalistic rule from the sets of neighbourhoods, as
+    /// [`neighbourhood_bit`] indices without the centre, that cause birth and
+    /// survival. Falls back to an outer-totalistic rule when only the number
+    /// of neighbours matters.
+    fn from_neighbourhoods(birth: &[bool; 512], survival: &[bool; 512]) -> Self {
+        let mut table = [0u64; 8];
+        let (mut birth_counts, mut survival_counts) = ([false; 9], [false; 9]);
+        for neighbourhood in (0..512).filter(|&n| n & CENTRE == 0) {
+            let count = neighbourhood.count_ones() as usize;
+            if birth[neighbourhood as usize] {
+                set_bit(&mut table, neighbourhood);
+                birth_counts[count] = true;
+            }
+            if survival[neighbourhood as usize] {
+                set_bit(&mut table, neighbourhood | CENTRE);
+                survival_counts[count] = true;
+            }
+        }
+
+        let rule = Self::new(birth_counts, survival_counts);
+        let totalistic = (0..512).all(|index| {
+            let live = (index & CENTRE != 0) as u32;
+            let neighbours = (index & NEIGHBOURS).count_ones();
+            (rule.next_state(live, neighbours) == 1) == get_bit(&table, index)
+        });
+        if totalistic {
+            rule
+        } else {
+            Self {
+                neighbourhoods: Some(table),
+                ..rule
+            }
+        }
+    }
+
+    /// The same rule with `states` states in total, counting dead and alive.
+    pub fn with_states(self, states: u32) -> Self {
+        Self { states, ..self }
+    }
+
+    pub fn states(&self) -> u32 {
+        self.states
+    }
+
+    /// Whether the rule depends only on the number of live neighbours.
+    pub fn is_totalistic(&self) -> bool {
+        self.neighbourhoods.is_none() && self.tree.is_none()
+    }
+
+    /// The Larger than Life part of the rule, if it counts beyond the eight
+    /// nearest neighbours.
+    pub fn range_rule(&self) -> Option<&RangeRule> {
+        self.range.as_deref()
+    }
+
+    /// The transition function of a rule loaded from a `.rule` file.
+    pub fn tree(&self) -> Option<&RuleTree> {
+        self.tree.as_deref()
+    }
+
+    /// Whether this is a two-state outer-totalistic rule on the eight nearest
+    /// neighbours, like Life itself.
+    pub fn is_life_like(&self) -> bool {
+        self.states == 2 && self.is_totalistic() && self.range.is_none()
+    }
+
+    /// Whether some arrangement of `neighbours` live neighbours causes birth.
+    pub fn birth(&self, neighbours: u32) -> bool {
+        match &self.range {
+            Some(range) => range.birth(neighbours),
+            None => self.birth[neighbours as usize],
+        }
+    }
+
+    /// Whether some arrangement of `neighbours` live neighbours allows
+    /// survival.
+    pub fn survival(&self, neighbours: u32) -> bool {
+        match &self.range {
+            Some(range) => range.survival(neighbours),
+            None => self.survival[neighbours as usize],
+        }
+    }
+
+    /// The next state of a cell in `state` with `neighbours` live neighbours.
+    /// Only meaningful for outer-totalistic rules.
+    pub fn next_state(&self, state: u32, neighbours: u32) -> u32 {
+        match state {
+            0 => self.birth(neighbours) as u32,
+            1 if self.survival(neighbours) => 1,
+            _ => (state + 1) % self.states,
+        }
+    }
+
+    /// Whether a dead cell with no live neighbours comes alive, so that empty
+    /// space does not stay empty.
+    pub fn is_b0(&self) -> bool {
+        self.transition(0, 0) != 0
+    }
+
+    /// The next state of a cell in `state` whose live neighbours are the set
+    /// bits of `neighbourhood`, as built with [`neighbourhood_bit`].
+    /// A tree rule sees every neighbour outside `neighbourhood` as dead.
+    pub fn transition(&self, state: u32, neighbourhood: u32) -> u32 {
+        if let Some(tree) = &self.tree {
+            return tree.next_state(|dx, dy| match (dx, dy) {
+                (0, 0) => state,
+                _ => (neighbourhood & neighbourhood_bit(dx, dy) != 0) as u32,
+            });
+        }
+        let Some(table) = &self.neighbourhoods else {
+            return self.next_state(state, (neighbourhood & NEIGHBOURS).count_ones());
+        };
+        let centre = if state == 1 { CENTRE } else { 0 };
+        match state {
+            0 | 1 if get_bit(table, neighbourhood & NEIGHBOURS | centre) => 1,
+            0 => 0,
+            _ => (state + 1) % self.states,
+        }
+    }
+
+    /// Writes the counts, with Hensel letters where needed, that make a dead
+    /// (`centre` 0) or live (`centre` [`CENTRE`]) cell alive next generation.
+    fn write_condition(&self, f: &mut fmt::Formatter<'_>, centre: u32) -> fmt::Result {
+        let counts = if centre == 0 {
+            &self.birth
+        } else {
+            &self.survival
+        };
+        for count in (0..9).filter(|&n| counts[n as usize]) {
+            write!(f, "{}", count)?;
+            let Some(table) = &self.neighbourhoods else {
+                continue;
+            };
+            let (present, absent): (Vec<_>, Vec<_>) = hensel_letters(count)
+                .partition(|&(_, neighbourhood)| get_bit(table, neighbourhood | centre));
+            let letters =
+                |letters: Vec<(char, u32)>| letters.into_iter().map(|(c, _)| c).collect::<String>();
+            if absent.is_empty() {
+                continue;
+            } else if present.len() > absent.len() {
+                write!(f, "-{}", letters(absent))?;
+            } else {
+                write!(f, "{}", letters(present))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::LIFE
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRuleError {
+    Empty,
+    InvalidCount(char),
+    UnexpectedCharacter(char),
+    MissingSeparator,
+    InvalidStates(String),
+    InvalidLetter(u32, char),
+    InvalidRangeRule(String),
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseRuleError::Empty => write!(f, "rule string is empty"),
+            ParseRuleError::InvalidCount(c) => {
+                write!(f, "'{}' is not a neighbour count between 0 and 8", c)
+            }
+            ParseRuleError::UnexpectedCharacter(c) => {
+                write!(f, "unexpected character '{}' in rule string", c)
+            }
+            ParseRuleError::MissingSeparator => {
+                write!(
+                    f,
+                    "expected B/S notation or survival/birth counts separated by '/'"
+                )
+            }
+            ParseRuleError::InvalidStates(states) => {
+                write!(
+                    f,
+                    "'{}' is not a number of states from 2 to {}",
+                    states, MAX_STATES
+                )
+            }
+            ParseRuleError::InvalidLetter(count, c) => {
+                write!(f, "'{}' is not a Hensel letter for {} neighbours", c, count)
+            }
+            ParseRuleError::InvalidRangeRule(part) => {
+                write!(f, "'{}' is not valid in a Larger than Life rule", part)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
+
+fn parse_counts(counts: &str) -> Result<[bool; 9], ParseRuleError> {
+    let mut table = [false; 9];
+    for c in counts.chars() {
+        match c.to_digit(10) {
+            Some(n) if n <= 8 => table[n as usize] = true,
+            Some(_) => return Err(ParseRuleError::InvalidCount(c)),
+            None => return Err(ParseRuleError::UnexpectedCharacter(c)),
+        }
+    }
+    Ok(table)
+}
+
+/// Parses the counts after a `B` or `S` into the set of neighbourhoods they
+/// cover. Each count may be followed by Hensel letters to cover only those
+/// arrangements, or by `-` and the letters to leave out.
+fn parse_condition(condition: &str) -> Result<[bool; 512], ParseRuleError> {
+    let mut set = [false; 512];
+    let mut chars = condition.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = match c.to_digit(10) {
+            Some(n) if n <= 8 => n,
+            Some(_) => return Err(ParseRuleError::InvalidCount(c)),
+            None => return Err(ParseRuleError::UnexpectedCharacter(c)),
+        };
+        let negated = chars.next_if_eq(&'-').is_some();
+        let mut letters = Vec::new();
+        while let Some(letter) = chars.next_if(char::is_ascii_alphabetic) {
+            match hensel_letters(count).find(|&(l, _)| l == letter) {
+                Some((_, neighbourhood)) => letters.extend(symmetries(neighbourhood)),
+                None => return Err(ParseRuleError::InvalidLetter(count, letter)),
+            }
+        }
+        if negated && letters.is_empty() {
+            return Err(ParseRuleError::UnexpectedCharacter('-'));
+        }
+        for neighbourhood in (0..512).filter(|&n| n & CENTRE == 0 && n.count_ones() == count) {
+            set[neighbourhood as usize] |=
+                letters.is_empty() || letters.contains(&neighbourhood) != negated;
+        }
+    }
+    Ok(set)
+}
+
+fn parse_states(states: &str) -> Result<u32, ParseRuleError> {
+    match states.parse() {
+        Ok(n) if (2..=MAX_STATES).contains(&n) => Ok(n),
+        _ => Err(ParseRuleError::InvalidStates(states.to_string())),
+    }
+}
+
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    /// Accepts `B36/S23`, `b3s23` and the older survival-first `23/36` notation.
+    /// Generations rules add a state count, as in `B2/S/C3`, `b2sc3` or `/2/3`.
+    /// Isotropic non-totalistic rules use Hensel letters, as in `B2-a/S12`,
+    /// and Larger than Life rules HROT notation, as in
+    /// `R5,C0,M1,S34..58,B34..45,NM`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        if s.is_empty() {
+            return Err(ParseRuleError::Empty);
+        }
+
+        if s.starts_with('r') {
+            let (range, states) = hrot::parse(&s)?;
+            let rule = match range.to_moore() {
+                Some((birth, survival)) => Self::new(birth, survival),
+                None => Self {
+                    range: Some(Box::new(range)),
+                    ..Self::new([false; 9], [false; 9])
+                },
+            };
+            return Ok(rule.with_states(states));
+        }
+
+        if !s.contains(['b', 's']) {
+            let mut parts = s.split('/');
+            return match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(survival), Some(birth), states, None) => {
+                    let rule = Self::new(parse_counts(birth)?, parse_counts(survival)?);
+                    Ok(rule.with_states(states.map_or(Ok(2), parse_states)?))
+                }
+                _ => Err(ParseRuleError::MissingSeparator),
+            };
+        }
+
+        let mut birth = None;
+        let mut survival = None;
+        let mut states = None;
+        let mut rest = s.as_str();
+        // Without slashes a trailing `c` and number is the state count rather
+        // than the Hensel letter c, so `b2s345c4` is B2/S345/C4 and not 5c
+        // followed by 4.
+        if let Some((conditions, count)) = s.rsplit_once('c') {
+            if !s.contains('/')
+                && conditions.ends_with(|c: char| c.is_ascii_digit() || c == 'b' || c == 's')
+                && !count.is_empty()
+                && count.chars().all(|c| c.is_ascii_digit())
+            {
+                states = Some(parse_states(count)?);
+                rest = conditions;
+            }
+        }
+        while let Some(prefix) = rest.chars().next() {
+            rest = &rest[prefix.len_utf8()..];
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '-' || HENSEL_LETTERS.contains(c)))
+                .unwrap_or(rest.len());
+            let condition = &rest[..end];
+            rest = &rest[end..];
+            match prefix {
+                'b' if birth.is_none() => birth = Some(parse_condition(condition)?),
+                's' if survival.is_none() => survival = Some(parse_condition(condition)?),
+                'c' | 'g' if states.is_none() => states = Some(parse_states(condition)?),
+                c => return Err(ParseRuleError::UnexpectedCharacter(c)),
+            }
+            rest = rest.strip_prefix('/').unwrap_or(rest);
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => {
+                Ok(Self::from_neighbourhoods(&birth, &survival).with_states(states.unwrap_or(2)))
+            }
+            _ => Err(ParseRuleError::MissingSeparator),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(range) = &self.range {
+            return hrot::write(range, self.states, f);
+        }
+        if let Some(tree) = &self.tree {
+            return write!(f, "{}", tree.name);
+        }
+        write!(f, "B")?;
+        self.write_condition(f, 0)?;
+        write!(f, "/S")?;
+        self.write_condition(f, CENTRE)?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NW: (i32, i32) = (-1, -1);
+    const N: (i32, i32) = (0, -1);
+    const NE: (i32, i32) = (1, -1);
+    const W: (i32, i32) = (-1, 0);
+    const E: (i32, i32) = (1, 0);
+    const SW: (i32, i32) = (-1, 1);
+    const S: (i32, i32) = (0, 1);
+    const SE: (i32, i32) = (1, 1);
+
+    type Cells = &'static [(i32, i32)];
+
+    /// One neighbourhood for each Hensel letter, as drawn on LifeWiki.
+    const LETTERS: &[(u32, char, Cells)] = &[
+        (1, 'c', &[NW]),
+        (1, 'e', &[N]),
+        (2, 'c', &[NW, NE]),
+        (2, 'e', &[N, W]),
+        (2, 'k', &[NW, E]),
+        (2, 'a', &[NW, N]),
+        (2, 'i', &[W, E]),
+        (2, 'n', &[NE, SW]),
+        (3, 'c', &[NW, NE, SW]),
+        (3, 'e', &[N, W, E]),
+        (3, 'k', &[N, E, SW]),
+        (3, 'a', &[NW, N, W]),
+        (3, 'i', &[NW, N, NE]),
+        (3, 'n', &[NW, NE, W]),
+        (3, 'y', &[NW, E, SW]),
+        (3, 'q', &[N, NE, SW]),
+        (3, 'j', &[N, NE, W]),
+        (3, 'r', &[NW, W, E]),
+        (4, 'c', &[NW, NE, SW, SE]),
+        (4, 'e', &[N, W, E, S]),
+        (4, 'k', &[NW, N, E, SW]),
+        (4, 'a', &[NW, N, NE, W]),
+        (4, 'i', &[NW, NE, W, E]),
+        (4, 'n', &[NW, N, NE, SW]),
+        (4, 'y', &[NW, NE, E, SW]),
+        (4, 'q', &[N, NE, E, SW]),
+        (4, 'j', &[N, W, E, SW]),
+        (4, 'r', &[NW, N, W, E]),
+        (4, 't', &[NW, W, E, SW]),
+        (4, 'w', &[N, NE, W, SW]),
+        (4, 'z', &[NE, W, E, SW]),
+    ];
+
+    #[test]
+    fn hensel_letters_match_golly() {
+        for &(count, letter, cells) in LETTERS {
+            let neighbourhood = cells
+                .iter()
+                .map(|&(dx, dy)| neighbourhood_bit(dx, dy))
+                .sum();
+            let (_, representative) = hensel_letters(count).find(|&(l, _)| l == letter).unwrap();
+            assert!(
+                symmetries(representative).any(|n| n == neighbourhood),
+                "{}{}",
+                count,
+                letter
+            );
+            // Below four, the complement is the same letter with the
+            // opposite count.
+            if count == 4 {
+                continue;
+            }
+            let (_, complement) = hensel_letters(8 - count)
+                .find(|&(l, _)| l == letter)
+                .unwrap();
+            assert!(symmetries(complement).any(|n| n == neighbourhood ^ NEIGHBOURS));
+        }
+    }
+
+    #[test]
+    fn hensel_letters_cover_every_neighbourhood_once() {
+        for count in 1..=7 {
+            let mut seen = [false; 512];
+            for (_, neighbourhood) in hensel_letters(count) {
+                for n in symmetries(neighbourhood) {
+                    seen[n as usize] = true;
+                }
+            }
+            let expected = (0..512u32).filter(|&n| n & CENTRE == 0 && n.count_ones() == count);
+            assert!(
+                expected.clone().all(|n| seen[n as usize]),
+                "count {}",
+                count
+            );
+            assert_eq!(
+                seen.iter().filter(|&&s| s).count(),
+                expected.count(),
+                "count {}",
+                count
+            );
+        }
+    }
+
+    #[test]
+    fn hensel_rules_run_the_right_neighbourhoods() {
+        // B3y: only the Y shape gives birth.
+        let rule: Rule = "B3y/S".parse().unwrap();
+        let bits = |cells: &[(i32, i32)]| cells.iter().map(|&(x, y)| neighbourhood_bit(x, y)).sum();
+        assert_eq!(rule.transition(0, bits(&[NW, E, SW])), 1);
+        assert_eq!(rule.transition(0, bits(&[N, NE, W])), 0);
+        assert_eq!(rule.transition(0, bits(&[NW, W, E])), 0);
+        assert_eq!(rule.to_string(), "B3y/S");
+    }
+
+    #[test]
+    fn state_counts_stop_at_what_a_tree_can_hold() {
+        assert_eq!("B2/S/C256".parse::<Rule>().unwrap().states(), 256);
+        assert_eq!("/2/256".parse::<Rule>().unwrap().states(), 256);
+        for rule in [
+            "B2/S/C257",
+            "B2/S/C1000",
+            "/2/1000",
+            "B2/S/C1",
+            "R1,C1000,S2,B3",
+        ] {
+            assert!(
+                matches!(rule.parse::<Rule>(), Err(ParseRuleError::InvalidStates(_))),
+                "{}",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn reads_a_trailing_state_count_without_slashes() {
+        let generations: Rule = "B3/S23/C3".parse().unwrap();
+        assert_eq!("b3s23c3".parse::<Rule>().unwrap(), generations);
+        assert_eq!("b2sc3".parse::<Rule>().unwrap().to_string(), "B2/S/C3");
+        // With slashes, c after a count is still a Hensel letter.
+        let hensel: Rule = "B3/S2c3".parse().unwrap();
+        assert_eq!(hensel.states(), 2);
+        assert!(!hensel.is_totalistic());
+        assert!("b3s23c"
+            .parse::<Rule>()
+            .is_ok_and(|rule| rule.states() == 2));
+    }
+
+    #[test]
+    fn parses_each_notation_for_life() {
+        for rule in ["B3/S23", "23/3", "b3s23", "b3/s23", " S23/B3 ", "B3S23"] {
+            assert_eq!(rule.parse::<Rule>(), Ok(Rule::LIFE), "{}", rule);
+        }
+        let highlife: Rule = "23/36".parse().unwrap();
+        assert_eq!(highlife.to_string(), "B36/S23");
+        assert!(highlife.birth(6) && !highlife.survival(6));
+        let seeds: Rule = "B2/S".parse().unwrap();
+        assert_eq!(seeds.to_string(), "B2/S");
+        assert_eq!(seeds.next_state(1, 2), 0);
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        assert_eq!("".parse::<Rule>(), Err(ParseRuleError::Empty));
+        assert_eq!(
+            "B9/S23".parse::<Rule>(),
+            Err(ParseRuleError::InvalidCount('9'))
+        );
+        assert_eq!("239".parse::<Rule>(), Err(ParseRuleError::MissingSeparator));
+        assert_eq!("B3".parse::<Rule>(), Err(ParseRuleError::MissingSeparator));
+        assert_eq!(
+            "B3/S2x".parse::<Rule>(),
+            Err(ParseRuleError::UnexpectedCharacter('x'))
+        );
+        assert_eq!(
+            "B3/S23/B3".parse::<Rule>(),
+            Err(ParseRuleError::UnexpectedCharacter('b'))
+        );
+    }
+}
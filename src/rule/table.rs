@@ -0,0 +1,598 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    file::RuleFileError,
+    tree::{RuleTree, TreeBuilder, TreeNeighbourhood, MAX_STATES},
+};
+
+/// A set of states, one bit each.
+type StateSet = [u64; 4];
+
+fn contains(set: &StateSet, state: u32) -> bool {
+    set[state as usize / 64] >> (state % 64) & 1 == 1
+}
+
+fn single(state: u32) -> StateSet {
+    let mut set = [0; 4];
+    set[state as usize / 64] |= 1 << (state % 64);
+    set
+}
+
+/// The neighbourhoods a rule table can be written for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TableNeighbourhood {
+    VonNeumann,
+    Moore,
+    /// Emulated on the square grid by ignoring the north-east and south-west
+    /// neighbours.
+    Hexagonal,
+}
+
+impl TableNeighbourhood {
+    /// Offsets of the neighbours in the order a transition lists them,
+    /// clockwise from north. The centre comes before them.
+    fn ring(self) -> &'static [(i32, i32)] {
+        match self {
+            TableNeighbourhood::VonNeumann => &[(0, -1), (1, 0), (0, 1), (-1, 0)],
+            TableNeighbourhood::Moore => &[
+                (0, -1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+                (-1, 0),
+                (-1, -1),
+            ],
+            TableNeighbourhood::Hexagonal => &[(0, -1), (1, 0), (1, 1), (0, 1), (-1, 0), (-1, -1)],
+        }
+    }
+
+    fn tree(self) -> TreeNeighbourhood {
+        match self {
+            TableNeighbourhood::VonNeumann => TreeNeighbourhood::VonNeumann,
+            TableNeighbourhood::Moore | TableNeighbourhood::Hexagonal => TreeNeighbourhood::Moore,
+        }
+    }
+}
+
+/// How a transition is repeated around the neighbourhood.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Symmetry {
+    /// Rotations by multiples of `1/n` of a turn, and their reflections if
+    /// `reflect` is set.
+    Rotate { n: usize, reflect: bool },
+    /// A left-right mirror image.
+    ReflectHorizontal,
+    /// Every ordering of the neighbours.
+    Permute,
+}
+
+impl Symmetry {
+    fn parse(symmetry: &str, ring: usize) -> Option<Self> {
+        let symmetry = match symmetry {
+            "none" => Symmetry::Rotate {
+                n: 1,
+                reflect: false,
+            },
+            "reflect_horizontal" => Symmetry::ReflectHorizontal,
+            "permute" => Symmetry::Permute,
+            _ => {
+                let rotate = symmetry.strip_prefix("rotate")?;
+                let (n, reflect) = match rotate.strip_suffix("reflect") {
+                    Some(n) => (n, true),
+                    None => (rotate, false),
+                };
+                Symmetry::Rotate {
+                    n: n.parse().ok()?,
+                    reflect,
+                }
+            }
+        };
+        match symmetry {
+            Symmetry::Rotate { n, .. } if n == 0 || !ring.is_multiple_of(n) => None,
+            symmetry => Some(symmetry),
+        }
+    }
+
+    /// The distinct rearrangements of `ring` under this symmetry.
+    fn apply<T: Clone + Eq + std::hash::Hash>(self, ring: &[T]) -> Vec<Vec<T>> {
+        let len = ring.len();
+        let mut seen = HashSet::new();
+        let mut push = |arrangement: Vec<T>| {
+            if seen.insert(arrangement.clone()) {
+                Some(arrangement)
+            } else {
+                None
+            }
+        };
+        let reflected = |ring: &[T]| (0..len).map(|i| ring[(len - i) % len].clone()).collect();
+        match self {
+            Symmetry::Rotate { n, reflect } => {
+                let mut result = Vec::new();
+                for turn in 0..n {
+                    let rotated: Vec<T> = (0..len)
+                        .map(|i| ring[(i + turn * len / n) % len].clone())
+                        .collect();
+                    if reflect {
+                        result.extend(push(reflected(&rotated)));
+                    }
+                    result.extend(push(rotated));
+                }
+                result
+            }
+            Symmetry::ReflectHorizontal => [ring.to_vec(), reflected(ring)]
+                .into_iter()
+                .filter_map(push)
+                .collect(),
+            Symmetry::Permute => {
+                let mut result = Vec::new();
+                permutations(&mut ring.to_vec(), 0, &mut |arrangement| {
+                    result.extend(push(arrangement.to_vec()))
+                });
+                result
+            }
+        }
+    }
+}
+
+/// Visits each distinct ordering of `items[start..]` once.
+fn permutations<T: Eq>(items: &mut [T], start: usize, visit: &mut impl FnMut(&[T])) {
+    if start == items.len() {
+        visit(items);
+        return;
+    }
+    for i in start..items.len() {
+        if items[start..i].contains(&items[i]) {
+            continue;
+        }
+        items.swap(start, i);
+        permutations(items, start + 1, visit);
+        items.swap(start, i);
+    }
+}
+
+/// One input of a transition as written.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Input {
+    States(StateSet),
+    Variable(String),
+}
+
+/// Splits a transition at commas outside braces, or into single digits if
+/// it has no commas.
+fn tokens(text: &str) -> Vec<String> {
+    if !text.contains(',') {
+        return text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(String::from)
+            .collect();
+    }
+    let mut tokens = vec![String::new()];
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                tokens.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        tokens.last_mut().unwrap().push(c);
+    }
+    tokens
+        .iter()
+        .map(|token| token.trim().to_string())
+        .collect()
+}
+
+/// The states listed in `{...}` or named by a single state or variable.
+fn parse_states(
+    token: &str,
+    states: u32,
+    variables: &HashMap<String, StateSet>,
+) -> Result<StateSet, String> {
+    let items = match token.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+        Some(items) => items.split(',').map(str::trim).collect(),
+        None => vec![token],
+    };
+    let mut set = [0; 4];
+    for item in items {
+        let item_set = match (item.parse::<u32>(), variables.get(item)) {
+            (Ok(state), _) if state < states => single(state),
+            (_, Some(&variable)) => variable,
+            _ => return Err(format!("'{}' is not a state or variable", item)),
+        };
+        for (word, item_word) in set.iter_mut().zip(item_set) {
+            *word |= item_word;
+        }
+    }
+    Ok(set)
+}
+
+/// A transition with its bound variables expanded, inputs in table order.
+struct Transition {
+    inputs: Vec<StateSet>,
+    output: u32,
+}
+
+/// Expands the variables that must take the same value everywhere they
+/// appear, which are those used more than once or in the output.
+fn expand(
+    inputs: &[Input],
+    output: &Input,
+    variables: &HashMap<String, StateSet>,
+    states: u32,
+) -> Vec<Transition> {
+    let mut bound = Vec::new();
+    for input in inputs.iter().chain([output]) {
+        if let Input::Variable(name) = input {
+            let uses = inputs
+                .iter()
+                .chain([output])
+                .filter(|&i| i == input)
+                .count();
+            if uses > 1 && !bound.contains(name) {
+                bound.push(name.clone());
+            }
+        }
+    }
+
+    let choices = bound
+        .iter()
+        .map(|name| {
+            (0..states)
+                .filter(|&state| contains(&variables[name], state))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    if choices.iter().any(Vec::is_empty) {
+        return Vec::new();
+    }
+
+    let mut transitions = Vec::new();
+    let mut indices = vec![0; bound.len()];
+    loop {
+        let resolve = |input: &Input| match input {
+            Input::States(set) => *set,
+            Input::Variable(name) => match bound.iter().position(|b| b == name) {
+                Some(i) => single(choices[i][indices[i]]),
+                None => variables[name],
+            },
+        };
+        let output = resolve(output);
+        if let Some(output) = (0..states).find(|&state| contains(&output, state)) {
+            transitions.push(Transition {
+                inputs: inputs.iter().map(resolve).collect(),
+                output,
+            });
+        }
+
+        // Step through every combination of values for the bound variables.
+        let Some(i) = (0..indices.len()).find(|&i| indices[i] + 1 < choices[i].len()) else {
+            return transitions;
+        };
+        indices[i] += 1;
+        indices[..i].fill(0);
+    }
+}
+
+/// Parses the numbered lines of an `@TABLE` section that starts on line
+/// `header`, and compiles it into a tree.
+pub(super) fn parse<'a>(
+    name: &str,
+    header: usize,
+    lines: impl IntoIterator<Item = (usize, &'a str)>,
+) -> Result<RuleTree, RuleFileError> {
+    let mut states = None;
+    let mut neighbourhood = TableNeighbourhood::Moore;
+    let mut symmetry = Symmetry::Rotate {
+        n: 1,
+        reflect: false,
+    };
+    let mut variables = HashMap::new();
+    let mut transitions = Vec::new();
+    for (line, text) in lines {
+        let text = text.split('#').next().unwrap_or("").trim();
+        if text.is_empty() {
+            continue;
+        }
+        let error = |message: String| RuleFileError::syntax(line, message);
+
+        if let Some((key, value)) = text.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" => match value.parse() {
+                    Ok(n) if (2..=MAX_STATES).contains(&n) => states = Some(n),
+                    _ => return Err(error(format!("invalid number of states '{}'", value))),
+                },
+                "neighborhood" => {
+                    neighbourhood = match value {
+                        "vonNeumann" => TableNeighbourhood::VonNeumann,
+                        "Moore" => TableNeighbourhood::Moore,
+                        "hexagonal" => TableNeighbourhood::Hexagonal,
+                        _ => return Err(error(format!("unsupported neighborhood '{}'", value))),
+                    }
+                }
+                "symmetries" => {
+                    symmetry = Symmetry::parse(value, neighbourhood.ring().len())
+                        .ok_or_else(|| error(format!("unsupported symmetries '{}'", value)))?
+                }
+                key => return Err(error(format!("unknown setting '{}'", key))),
+            }
+            continue;
+        }
+
+        let Some(states) = states else {
+            return Err(error(
+                "n_states must come before the transitions".to_string(),
+            ));
+        };
+        if let Some(declaration) = text.strip_prefix("var ") {
+            let (variable, set) = declaration
+                .split_once('=')
+                .ok_or_else(|| error(format!("invalid variable '{}'", declaration)))?;
+            let set = parse_states(set.trim(), states, &variables).map_err(error)?;
+            variables.insert(variable.trim().to_string(), set);
+            continue;
+        }
+
+        let tokens = tokens(text);
+        if tokens.len() != neighbourhood.ring().len() + 2 {
+            return Err(error(format!(
+                "expected {} states in a transition",
+                neighbourhood.ring().len() + 2
+            )));
+        }
+        let inputs = tokens
+            .iter()
+            .map(|token| {
+                if variables.contains_key(token) {
+                    Ok(Input::Variable(token.clone()))
+                } else {
+                    parse_states(token, states, &variables).map(Input::States)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(error)?;
+        let (output, inputs) = inputs.split_last().unwrap();
+        for transition in expand(inputs, output, &variables, states) {
+            let (centre, ring) = transition.inputs.split_first().unwrap();
+            for ring in symmetry.apply(ring) {
+                let mut inputs = vec![*centre];
+                inputs.extend(ring);
+                transitions.push(Transition {
+                    inputs,
+                    output: transition.output,
+                });
+            }
+        }
+    }
+
+    let Some(states) = states else {
+        return Err(RuleFileError::syntax(header, "missing n_states"));
+    };
+    Ok(compile(name, states, neighbourhood, &transitions))
+}
+
+/// Builds the tree that applies the first transition matching each
+/// neighbourhood, leaving the centre unchanged if none do.
+fn compile(
+    name: &str,
+    states: u32,
+    neighbourhood: TableNeighbourhood,
+    transitions: &[Transition],
+) -> RuleTree {
+    let tree = neighbourhood.tree();
+    // For each cell the tree reads, where it appears in a transition.
+    let positions = tree
+        .offsets()
+        .iter()
+        .map(|&offset| match offset {
+            (0, 0) => Some(0),
+            offset => neighbourhood
+                .ring()
+                .iter()
+                .position(|&o| o == offset)
+                .map(|i| i + 1),
+        })
+        .collect::<Vec<_>>();
+
+    let mut builder = TreeBuilder::new(states);
+    let mut memo = HashMap::new();
+    let all = (0..transitions.len()).collect::<Vec<_>>();
+    let root = compile_node(&mut builder, &mut memo, transitions, &positions, 0, &all);
+    builder.build(name, tree, root)
+}
+
+/// The node for cells read from `level` on, given the transitions that
+/// still match the cells read so far.
+fn compile_node(
+    builder: &mut TreeBuilder,
+    memo: &mut HashMap<(usize, Vec<usize>), u32>,
+    transitions: &[Transition],
+    positions: &[Option<usize>],
+    level: usize,
+    candidates: &[usize],
+) -> u32 {
+    if let Some(&node) = memo.get(&(level, candidates.to_vec())) {
+        return node;
+    }
+    let entries = (0..builder.states())
+        .map(|state| {
+            let matching = candidates
+                .iter()
+                .copied()
+                .filter(|&t| {
+                    positions[level].is_none_or(|p| contains(&transitions[t].inputs[p], state))
+                })
+                .collect::<Vec<_>>();
+            if level + 1 == positions.len() {
+                matching.first().map_or(state, |&t| transitions[t].output)
+            } else {
+                compile_node(builder, memo, transitions, positions, level + 1, &matching)
+            }
+        })
+        .collect();
+    let node = builder.node(entries);
+    memo.insert((level, candidates.to_vec()), node);
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIREWORLD: &str = "\
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,2,3}
+var b={0,2,3}
+var c={0,2,3}
+var d={0,2,3}
+var e={0,2,3}
+var f={0,2,3}
+var g={0,2,3}
+var i={0,1,2,3}
+var j={0,1,2,3}
+var k={0,1,2,3}
+var l={0,1,2,3}
+var m={0,1,2,3}
+var n={0,1,2,3}
+var o={0,1,2,3}
+var p={0,1,2,3}
+1,i,j,k,l,m,n,o,p,2
+2,i,j,k,l,m,n,o,p,3
+3,1,a,b,c,d,e,f,g,1
+3,1,1,a,b,c,d,e,f,1
+";
+
+    fn table(text: &str) -> RuleTree {
+        parse("test", 1, text.lines().enumerate().map(|(i, l)| (i + 2, l))).unwrap()
+    }
+
+    /// The next state of a cell in state `centre` with the given neighbours,
+    /// every other cell being 0.
+    fn step(tree: &RuleTree, centre: u32, neighbours: &[((i32, i32), u32)]) -> u32 {
+        tree.next_state(|dx, dy| match (dx, dy) {
+            (0, 0) => centre,
+            offset => neighbours.iter().find(|n| n.0 == offset).map_or(0, |n| n.1),
+        })
+    }
+
+    #[test]
+    fn runs_wireworld() {
+        let tree = table(WIREWORLD);
+        assert_eq!(tree.states(), 4);
+        assert_eq!(step(&tree, 0, &[((0, -1), 1), ((1, 0), 1)]), 0);
+        assert_eq!(step(&tree, 1, &[((0, -1), 3)]), 2);
+        assert_eq!(step(&tree, 2, &[((0, -1), 1)]), 3);
+        assert_eq!(step(&tree, 3, &[]), 3);
+        assert_eq!(step(&tree, 3, &[((-1, 1), 1), ((1, 0), 2)]), 1);
+        assert_eq!(step(&tree, 3, &[((-1, -1), 1), ((1, 1), 1)]), 1);
+        let three = [((-1, -1), 1), ((0, -1), 1), ((1, 1), 1)];
+        assert_eq!(step(&tree, 3, &three), 3);
+    }
+
+    #[test]
+    fn rotate4reflect_gives_the_eight_symmetries_of_a_square() {
+        let ring = (0..8).collect::<Vec<_>>();
+        let symmetry = Symmetry::parse("rotate4reflect", 8).unwrap();
+        let arrangements = symmetry.apply(&ring);
+        assert_eq!(arrangements.len(), 8);
+        assert!(arrangements.contains(&vec![2, 3, 4, 5, 6, 7, 0, 1]));
+        assert!(arrangements.contains(&vec![0, 7, 6, 5, 4, 3, 2, 1]));
+        assert!(!arrangements.contains(&vec![1, 2, 3, 4, 5, 6, 7, 0]));
+        // Symmetric transitions aren't repeated.
+        assert_eq!(symmetry.apply(&[1, 0, 0, 0, 0, 0, 0, 0]).len(), 4);
+        assert_eq!(symmetry.apply(&[1, 0, 1, 0, 1, 0, 1, 0]).len(), 1);
+        assert_eq!(Symmetry::parse("rotate3", 8), None);
+    }
+
+    #[test]
+    fn permute_gives_each_ordering_once() {
+        let symmetry = Symmetry::parse("permute", 8).unwrap();
+        assert_eq!(symmetry.apply(&[1, 1, 0, 0, 0, 0, 0, 0]).len(), 28);
+        assert_eq!(symmetry.apply(&[1, 2, 3, 0]).len(), 24);
+
+        let tree = table("n_states:2\nsymmetries:permute\n0,1,1,0,0,0,0,0,0,1\n");
+        assert_eq!(step(&tree, 0, &[((-1, -1), 1), ((1, 1), 1)]), 1);
+        assert_eq!(step(&tree, 0, &[((0, 1), 1), ((1, 0), 1)]), 1);
+        assert_eq!(step(&tree, 0, &[((0, 1), 1)]), 0);
+    }
+
+    #[test]
+    fn hexagonal_tables_reflect_around_six_neighbours() {
+        let rotate = "n_states:2\nneighborhood:hexagonal\nsymmetries:rotate6\n0,1,1,0,1,0,0,1\n";
+        let reflect = rotate.replace("rotate6", "rotate6reflect");
+        let (rotate, reflect) = (table(rotate), table(&reflect));
+        // North, east and south, then the same turned a sixth clockwise.
+        let cells = [((0, -1), 1), ((1, 0), 1), ((0, 1), 1)];
+        let turned = [((1, 0), 1), ((1, 1), 1), ((-1, 0), 1)];
+        for tree in [&rotate, &reflect] {
+            assert_eq!(step(tree, 0, &cells), 1);
+            assert_eq!(step(tree, 0, &turned), 1);
+            // North-east and south-west aren't hexagon neighbours.
+            let ignored = [cells.as_slice(), &[((1, -1), 1), ((-1, 1), 1)]].concat();
+            assert_eq!(step(tree, 0, &ignored), 1);
+        }
+        // North, north-west and south is the mirror image.
+        let mirrored = [((0, -1), 1), ((-1, -1), 1), ((0, 1), 1)];
+        assert_eq!(step(&rotate, 0, &mirrored), 0);
+        assert_eq!(step(&reflect, 0, &mirrored), 1);
+        assert_eq!(
+            Symmetry::parse("rotate6reflect", 6)
+                .unwrap()
+                .apply(&[0, 1, 2, 3, 4, 5])
+                .len(),
+            12
+        );
+        assert_eq!(Symmetry::parse("rotate4", 6), None);
+    }
+
+    #[test]
+    fn variables_used_twice_take_one_value() {
+        let tree = table("n_states:3\nvar a={1,2}\nvar b={1,2}\n0,a,a,b,0,0,0,0,0,a\n");
+        // North and north-east must match; east can be anything in b.
+        assert_eq!(
+            step(&tree, 0, &[((0, -1), 1), ((1, -1), 1), ((1, 0), 2)]),
+            1
+        );
+        assert_eq!(
+            step(&tree, 0, &[((0, -1), 2), ((1, -1), 2), ((1, 0), 1)]),
+            2
+        );
+        assert_eq!(
+            step(&tree, 0, &[((0, -1), 1), ((1, -1), 2), ((1, 0), 1)]),
+            0
+        );
+        assert_eq!(step(&tree, 0, &[((0, -1), 1), ((1, -1), 1)]), 0);
+    }
+
+    #[test]
+    fn the_first_matching_transition_wins() {
+        let tree = table(
+            "n_states:3\n\
+             0,1,0,0,0,0,0,0,0,1\n\
+             0,{0,1},{0,1},{0,1},{0,1},{0,1},{0,1},{0,1},{0,1},2\n\
+             0,1,0,0,0,0,0,0,0,0\n",
+        );
+        assert_eq!(step(&tree, 0, &[((0, -1), 1)]), 1);
+        assert_eq!(step(&tree, 0, &[((1, 0), 1)]), 2);
+        assert_eq!(step(&tree, 0, &[((1, 0), 2)]), 0);
+        // Cells no transition matches keep their state.
+        assert_eq!(step(&tree, 2, &[((0, -1), 1)]), 2);
+    }
+
+    #[test]
+    fn reports_the_line_of_a_bad_transition() {
+        let error = parse("test", 1, [(2, "n_states:2"), (3, "0,1,0,1")]).unwrap_err();
+        assert!(matches!(error, RuleFileError::Syntax { line: 3, .. }));
+        let error = parse("test", 1, [(2, "0,0,0,0,0,0,0,0,0,1")]).unwrap_err();
+        assert!(matches!(error, RuleFileError::Syntax { line: 2, .. }));
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use super::file::RuleFileError;
+
+/// The largest number of states a rule tree or table may have.
+pub const MAX_STATES: u32 = 256;
+
+/// The cells a rule tree reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeNeighbourhood {
+    VonNeumann,
+    Moore,
+}
+
+impl TreeNeighbourhood {
+    /// Offsets in the order the tree reads them, ending with the centre.
+    pub fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            TreeNeighbourhood::VonNeumann => &[(0, -1), (-1, 0), (1, 0), (0, 1), (0, 0)],
+            TreeNeighbourhood::Moore => &[
+                (-1, -1),
+                (1, -1),
+                (-1, 1),
+                (1, 1),
+                (0, -1),
+                (-1, 0),
+                (1, 0),
+                (0, 1),
+                (0, 0),
+            ],
+        }
+    }
+}
+
+/// A transition function in the form of Golly's RuleTree: a decision
+/// diagram with one level per cell read, whose last level gives the next
+/// state of the centre.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleTree {
+    pub name: String,
+    states: u32,
+    neighbourhood: TreeNeighbourhood,
+    /// Nodes of `states` entries each. Entries of the last level are next
+    /// states; the others are the offsets of child nodes.
+    nodes: Vec<u32>,
+    root: u32,
+}
+
+impl RuleTree {
+    pub fn states(&self) -> u32 {
+        self.states
+    }
+
+    pub fn neighbourhood(&self) -> TreeNeighbourhood {
+        self.neighbourhood
+    }
+
+    /// The next state of a cell, given the state of the cell at each offset
+    /// from it.
+    pub fn next_state(&self, cell: impl Fn(i32, i32) -> u32) -> u32 {
+        self.neighbourhood
+            .offsets()
+            .iter()
+            .fold(self.root, |node, &(dx, dy)| {
+                self.nodes[(node + cell(dx, dy).min(self.states - 1)) as usize]
+            })
+    }
+}
+
+/// Builds a [`RuleTree`] from the bottom up, sharing identical nodes.
+pub(super) struct TreeBuilder {
+    states: u32,
+    nodes: Vec<u32>,
+    offsets: HashMap<Vec<u32>, u32>,
+}
+
+impl TreeBuilder {
+    pub fn new(states: u32) -> Self {
+        Self {
+            states,
+            nodes: Vec::new(),
+            offsets: HashMap::new(),
+        }
+    }
+
+    pub fn states(&self) -> u32 {
+        self.states
+    }
+
+    /// Adds a node with one entry per state, returning its offset.
+    pub fn node(&mut self, entries: Vec<u32>) -> u32 {
+        debug_assert_eq!(entries.len(), self.states as usize);
+        if let Some(&offset) = self.offsets.get(&entries) {
+            return offset;
+        }
+        let offset = self.nodes.len() as u32;
+        self.nodes.extend(&entries);
+        self.offsets.insert(entries, offset);
+        offset
+    }
+
+    pub fn build(self, name: &str, neighbourhood: TreeNeighbourhood, root: u32) -> RuleTree {
+        RuleTree {
+            name: name.to_string(),
+            states: self.states,
+            neighbourhood,
+            nodes: self.nodes,
+            root,
+        }
+    }
+}
+
+/// Parses the numbered lines of an `@TREE` section that starts on line
+/// `header`.
+pub(super) fn parse<'a>(
+    name: &str,
+    header: usize,
+    lines: impl IntoIterator<Item = (usize, &'a str)>,
+) -> Result<RuleTree, RuleFileError> {
+    let mut states = None;
+    let mut neighbourhood = None;
+    let mut builder = None;
+    // Offset and level of each node read so far.
+    let mut nodes: Vec<(u32, usize)> = Vec::new();
+    for (line, text) in lines {
+        let text = text.split('#').next().unwrap_or("").trim();
+        if text.is_empty() {
+            continue;
+        }
+        let error = |message: String| RuleFileError::syntax(line, message);
+        if let Some((key, value)) = text.split_once('=') {
+            let value = value.trim();
+            match key.trim() {
+                "num_states" => match value.parse() {
+                    Ok(n) if (2..=MAX_STATES).contains(&n) => {
+                        states = Some(n);
+                        builder = Some(TreeBuilder::new(n));
+                    }
+                    _ => return Err(error(format!("invalid number of states '{}'", value))),
+                },
+                "num_neighbors" => {
+                    neighbourhood = Some(match value {
+                        "4" => TreeNeighbourhood::VonNeumann,
+                        "8" => TreeNeighbourhood::Moore,
+                        _ => return Err(error(format!("unsupported neighbour count '{}'", value))),
+                    })
+                }
+                "num_nodes" => {}
+                key => return Err(error(format!("unknown setting '{}'", key))),
+            }
+            continue;
+        }
+
+        let (Some(states), Some(builder)) = (states, &mut builder) else {
+            return Err(error("num_states must come before the nodes".to_string()));
+        };
+        let numbers = text
+            .split_whitespace()
+            .map(str::parse::<u32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| error(format!("invalid node '{}'", text)))?;
+        let Some((&level, entries)) = numbers.split_first() else {
+            continue;
+        };
+        if entries.len() != states as usize {
+            return Err(error(format!("expected {} entries per node", states)));
+        }
+        let entries = match level {
+            1 if entries.iter().all(|&state| state < states) => entries.to_vec(),
+            1 => return Err(error("next state out of range".to_string())),
+            _ => entries
+                .iter()
+                .map(|&child| match nodes.get(child as usize) {
+                    Some(&(offset, child_level)) if child_level + 1 == level as usize => Ok(offset),
+                    _ => Err(error(format!("invalid child node {}", child))),
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        nodes.push((builder.node(entries), level as usize));
+    }
+
+    let (Some(neighbourhood), Some(builder)) = (neighbourhood, builder) else {
+        return Err(RuleFileError::syntax(
+            header,
+            "missing num_states or num_neighbors",
+        ));
+    };
+    match nodes.last() {
+        Some(&(root, level)) if level == neighbourhood.offsets().len() => {
+            Ok(builder.build(name, neighbourhood, root))
+        }
+        _ => Err(RuleFileError::syntax(
+            header,
+            "the last node must be the root of the tree",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Copies the state of the northern neighbour, reading north, west, east,
+    /// south and the centre.
+    const NORTH: &str = "\
+num_states=2
+num_neighbors=4
+num_nodes=9
+1 0 0
+1 1 1
+2 0 0
+2 1 1
+3 2 2
+3 3 3
+4 4 4
+4 5 5
+5 6 7
+";
+
+    fn tree(text: &str) -> Result<RuleTree, RuleFileError> {
+        parse("test", 1, text.lines().enumerate().map(|(i, l)| (i + 2, l)))
+    }
+
+    #[test]
+    fn reads_nodes_from_the_bottom_up() {
+        let tree = tree(NORTH).unwrap();
+        assert_eq!(tree.states(), 2);
+        assert_eq!(tree.neighbourhood(), TreeNeighbourhood::VonNeumann);
+        for (north, others) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let next = tree.next_state(|_, dy| if dy == -1 { north } else { others });
+            assert_eq!(next, north);
+        }
+        // Nodes with the same entries are stored once, whatever their level.
+        assert_eq!(tree.nodes.len(), 6 * 2);
+    }
+
+    #[test]
+    fn rejects_malformed_trees() {
+        let error = |text: &str| match tree(text) {
+            Err(RuleFileError::Syntax { line, .. }) => line,
+            result => panic!("expected a syntax error, got {:?}", result),
+        };
+        // A child from the wrong level.
+        assert_eq!(error(&NORTH.replace("3 2 2", "3 0 2")), 9);
+        assert_eq!(error(&NORTH.replace("1 1 1", "1 1 2")), 6);
+        assert_eq!(
+            error(&NORTH.replace("num_neighbors=4", "num_neighbors=6")),
+            3
+        );
+        // The root must read every neighbour.
+        assert_eq!(error(NORTH.trim_end().rsplit_once('\n').unwrap().0), 1);
+    }
+}
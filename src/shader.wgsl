@@ -18,6 +18,13 @@ struct PVMat {
 [[group(0), binding(0)]]
 var<uniform> pv_mat: PVMat;
 
+// Colours given by a rule file. Entries with zero alpha are unset.
+struct Palette {
+    colours: array<vec4<f32>, 256>;
+};
+[[group(0), binding(1)]]
+var<uniform> palette: Palette;
+
 [[stage(vertex)]]
 fn vs_main(
     [[location(0)]] position: vec2<f32>,
@@ -36,8 +43,40 @@ fn vs_main(
     return out;
 }
 
+// Overlay flags set by `Game::draw_overlays`.
+let SELECTED_FLAG: u32 = 0x80000000u;
+let PREVIEW_FLAG: u32 = 0x40000000u;
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> vec3<f32> {
+    let p = abs(fract(vec3<f32>(hue) + vec3<f32>(1.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - vec3<f32>(3.0));
+    let rgb = clamp(p - vec3<f32>(1.0), vec3<f32>(0.0), vec3<f32>(1.0));
+    return value * mix(vec3<f32>(1.0), rgb, vec3<f32>(saturation));
+}
+
+// Dead cells are black and live cells white unless the palette says
+// otherwise. The decaying states of a Generations rule step around the hue
+// circle so neighbouring states differ.
+fn state_colour(state: u32) -> vec3<f32> {
+    if (state < 256u && palette.colours[state].a > 0.0) {
+        return palette.colours[state].rgb;
+    }
+    if (state == 0u) {
+        return vec3<f32>(0.0);
+    }
+    if (state == 1u) {
+        return vec3<f32>(1.0);
+    }
+    return hsv_to_rgb(fract(0.08 + f32(state - 2u) * 0.618034), 0.75, 0.9);
+}
+
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    let state = f32(in.state);
-    return vec4<f32>(state, state, state, 1.0);
+    var colour = state_colour(in.state & 0xffffu);
+    if ((in.state & SELECTED_FLAG) != 0u) {
+        colour = mix(colour, vec3<f32>(0.2, 0.4, 1.0), vec3<f32>(0.35));
+    }
+    if ((in.state & PREVIEW_FLAG) != 0u) {
+        colour = mix(colour, vec3<f32>(0.2, 1.0, 0.3), vec3<f32>(0.6));
+    }
+    return vec4<f32>(colour, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,314 @@
+use std::collections::{HashMap, HashSet};
+
+use rayon::prelude::*;
+
+use crate::{
+    engine::{Engine, Rect},
+    packed::next_word,
+    rule::Rule,
+    universe::Topology,
+};
+
+/// Width and height of a tile in cells. One row of a tile is one `u64`.
+const TILE: usize = 64;
+const TILE_BITS: i64 = TILE.trailing_zeros() as i64;
+
+type Tile = [u64; TILE];
+
+/// A two-state universe on an unbounded plane, stored as a hash map of
+/// 64x64 bit-packed tiles. Tiles are created when a cell in them comes
+/// alive and dropped as soon as they are empty, so memory follows the
+/// population rather than the extent of the pattern. Rules with B0, which
+/// would fill the whole plane, are rejected.
+pub struct SparseUniverse {
+    tiles: HashMap<(i64, i64), Box<Tile>>,
+    rule: Rule,
+    generation: u64,
+    parallel: bool,
+}
+
+impl Default for SparseUniverse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SparseUniverse {
+    pub fn new() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            rule: Rule::default(),
+            generation: 0,
+            parallel: true,
+        }
+    }
+
+    /// Number of tiles currently allocated.
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Splits a cell position into its tile and the row and bit inside it.
+    fn locate(x: i64, y: i64) -> ((i64, i64), usize, u32) {
+        (
+            (x >> TILE_BITS, y >> TILE_BITS),
+            (y & (TILE as i64 - 1)) as usize,
+            (x & (TILE as i64 - 1)) as u32,
+        )
+    }
+
+    fn step_tile(
+        &self,
+        (tx, ty): (i64, i64),
+        birth: &[u32],
+        survival: &[u32],
+    ) -> Option<Box<Tile>> {
+        let neighbourhood: [[Option<&Tile>; 3]; 3] = std::array::from_fn(|row| {
+            std::array::from_fn(|col| {
+                self.tiles
+                    .get(&(tx + col as i64 - 1, ty + row as i64 - 1))
+                    .map(|tile| &**tile)
+            })
+        });
+
+        // Row `y` of column `col` of the neighbourhood, where `y` may run one
+        // row into the tiles above and below.
+        let word = |col: usize, y: isize| {
+            let (row, y) = match y {
+                -1 => (0, TILE - 1),
+                y if y == TILE as isize => (2, 0),
+                y => (1, y as usize),
+            };
+            neighbourhood[row][col].map_or(0, |tile| tile[y])
+        };
+        let shifts = |y: isize| {
+            let centre = word(1, y);
+            (
+                (centre << 1) | (word(0, y) >> (TILE - 1)),
+                centre,
+                (centre >> 1) | ((word(2, y) & 1) << (TILE - 1)),
+            )
+        };
+
+        let mut next = Box::new([0; TILE]);
+        for (y, out) in next.iter_mut().enumerate() {
+            let y = y as isize;
+            *out = next_word(shifts(y - 1), shifts(y), shifts(y + 1), birth, survival);
+        }
+        next.iter().any(|&w| w != 0).then_some(next)
+    }
+
+    /// Calls `f` for every live cell of a tile, skipping those outside `clip`.
+    fn visit_tile(
+        clip: Option<&Rect>,
+        (tx, ty): (i64, i64),
+        tile: &Tile,
+        f: &mut impl FnMut(i64, i64),
+    ) {
+        let (x0, y0) = (tx << TILE_BITS, ty << TILE_BITS);
+        for (y, &word) in tile.iter().enumerate() {
+            let mut bits = word;
+            while bits != 0 {
+                let x = x0 + bits.trailing_zeros() as i64;
+                if clip.is_none_or(|rect| rect.contains(x, y0 + y as i64)) {
+                    f(x, y0 + y as i64);
+                }
+                bits &= bits - 1;
+            }
+        }
+    }
+}
+
+impl Engine for SparseUniverse {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String> {
+        if !rule.is_life_like() {
+            return Err("the sparse backend only runs Life-like rules".to_string());
+        }
+        if rule.is_b0() {
+            return Err("the sparse backend can't run B0 rules".to_string());
+        }
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        None
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn get(&self, x: i64, y: i64) -> u32 {
+        let (key, row, bit) = Self::locate(x, y);
+        self.tiles
+            .get(&key)
+            .map_or(0, |tile| ((tile[row] >> bit) & 1) as u32)
+    }
+
+    fn set(&mut self, x: i64, y: i64, state: u32) {
+        let (key, row, bit) = Self::locate(x, y);
+        if state > 0 {
+            self.tiles.entry(key).or_insert_with(|| Box::new([0; TILE]))[row] |= 1 << bit;
+        } else if let Some(tile) = self.tiles.get_mut(&key) {
+            tile[row] &= !(1 << bit);
+            if tile.iter().all(|&w| w == 0) {
+                self.tiles.remove(&key);
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        let birth = (0..9).filter(|&n| self.rule.birth(n)).collect::<Vec<_>>();
+        let survival = (0..9)
+            .filter(|&n| self.rule.survival(n))
+            .collect::<Vec<_>>();
+
+        // Only tiles next to a live tile can contain live cells next generation.
+        let candidates = self
+            .tiles
+            .keys()
+            .flat_map(|&(tx, ty)| {
+                (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (tx + dx, ty + dy)))
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        self.tiles = if self.parallel {
+            candidates
+                .into_par_iter()
+                .filter_map(|key| Some((key, self.step_tile(key, &birth, &survival)?)))
+                .collect()
+        } else {
+            candidates
+                .into_iter()
+                .filter_map(|key| Some((key, self.step_tile(key, &birth, &survival)?)))
+                .collect()
+        };
+        self.generation += 1;
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u32)> {
+        let mut cells = Vec::new();
+        for (&key, tile) in &self.tiles {
+            Self::visit_tile(None, key, tile, &mut |x, y| cells.push((x, y, 1)));
+        }
+        cells
+    }
+
+    fn population(&self) -> u64 {
+        self.tiles
+            .values()
+            .flat_map(|tile| tile.iter())
+            .map(|w| w.count_ones() as u64)
+            .sum()
+    }
+
+    /// Visits only the tiles overlapping `rect`.
+    fn read_region(&self, rect: Rect, out: &mut [u32]) {
+        out.fill(0);
+        if rect.width == 0 || rect.height == 0 {
+            return;
+        }
+        let (tx0, ty0) = (rect.x >> TILE_BITS, rect.y >> TILE_BITS);
+        let tx1 = (rect.x + rect.width as i64 - 1) >> TILE_BITS;
+        let ty1 = (rect.y + rect.height as i64 - 1) >> TILE_BITS;
+
+        let mut write = |x: i64, y: i64| {
+            out[((y - rect.y) as u64 * rect.width + (x - rect.x) as u64) as usize] = 1;
+        };
+        let visible = ((tx1 - tx0 + 1) as u64).saturating_mul((ty1 - ty0 + 1) as u64);
+        if visible <= self.tiles.len() as u64 {
+            for ty in ty0..=ty1 {
+                for tx in tx0..=tx1 {
+                    if let Some(tile) = self.tiles.get(&(tx, ty)) {
+                        Self::visit_tile(Some(&rect), (tx, ty), tile, &mut write);
+                    }
+                }
+            }
+        } else {
+            for (&(tx, ty), tile) in &self.tiles {
+                if (tx0..=tx1).contains(&tx) && (ty0..=ty1).contains(&ty) {
+                    Self::visit_tile(Some(&rect), (tx, ty), tile, &mut write);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::universe::Universe;
+
+    #[test]
+    fn matches_the_dense_engine() {
+        // A soup straddling the tiles around the origin, on a dense plane
+        // big enough that nothing reaches its edges.
+        const OFFSET: i64 = 128;
+        for rule in ["B3/S23", "B36/S23", "B2/S"] {
+            let mut dense = Universe::new(256, 256);
+            dense.set_topology(Topology::Plane).unwrap();
+            let mut sparse = SparseUniverse::new();
+            let mut seed = 7u32;
+            for y in -40..40 {
+                for x in -40..40 {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    let state = (seed >> 30 == 0) as u32;
+                    dense.set(x + OFFSET, y + OFFSET, state);
+                    sparse.set(x, y, state);
+                }
+            }
+            for engine in [&mut dense as &mut dyn Engine, &mut sparse] {
+                engine.set_rule(rule.parse().unwrap()).unwrap();
+            }
+            let generations = if rule == "B2/S" { 20 } else { 100 };
+            for generation in 0..generations {
+                dense.step();
+                sparse.step();
+                let mut cells = sparse
+                    .live_cells()
+                    .into_iter()
+                    .map(|(x, y, state)| (x + OFFSET, y + OFFSET, state))
+                    .collect::<Vec<_>>();
+                cells.sort_unstable_by_key(|&(x, y, _)| (y, x));
+                assert_eq!(cells, dense.live_cells(), "{rule} at {generation}");
+            }
+        }
+    }
+
+    #[test]
+    fn drops_empty_tiles() {
+        let mut sparse = SparseUniverse::new();
+        sparse.set(-1, -1, 1);
+        sparse.set(100, 100, 1);
+        assert_eq!(sparse.tile_count(), 2);
+        sparse.step();
+        assert_eq!(sparse.population(), 0);
+        assert_eq!(sparse.tile_count(), 0);
+    }
+
+    #[test]
+    fn rejects_b0_rules() {
+        let mut sparse = SparseUniverse::new();
+        assert!(sparse.set_rule("B0/S8".parse().unwrap()).is_err());
+        assert!(sparse.set_rule("B3/S23".parse().unwrap()).is_ok());
+    }
+}
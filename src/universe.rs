@@ -0,0 +1,700 @@
+use std::{fmt, str::FromStr};
+
+use rayon::prelude::*;
+
+use crate::{
+    engine::{Engine, Rect},
+    geometry::Geometry,
+    rule::{hrot::Block, neighbourhood_bit, tree::RuleTree, Rule},
+};
+
+/// Which point of the grid stays fixed when it is resized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Centre,
+}
+
+impl FromStr for Anchor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "top-left" | "topleft" => Ok(Anchor::TopLeft),
+            "centre" | "center" => Ok(Anchor::Centre),
+            _ => Err(format!(
+                "unknown anchor '{}', expected top-left or centre",
+                s
+            )),
+        }
+    }
+}
+
+/// How the edges of the grid connect to each other. The names follow Golly's
+/// bounded grids.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Topology {
+    /// Opposite edges are joined.
+    #[default]
+    Torus,
+    /// Cells beyond the edges are permanently dead.
+    Plane,
+    /// A torus whose top and bottom edges are joined with a horizontal
+    /// offset: leaving through the bottom edge moves a cell this many
+    /// columns to the right.
+    ShiftedTorus(i64),
+    /// Left and right edges are joined as on a torus; top and bottom edges
+    /// are joined with a twist, so column `x` meets column `width - 1 - x`.
+    KleinBottle,
+    /// Both pairs of opposite edges are joined with a twist.
+    CrossSurface,
+    /// The top edge is joined to the left edge and the right edge to the
+    /// bottom edge. The grid must be square for the edges to meet.
+    Sphere,
+}
+
+impl Topology {
+    /// The smallest size of at least `width` by `height` whose edges can be
+    /// joined: a sphere is squared up, other topologies take any size.
+    pub fn fit(self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Topology::Sphere => (width.max(height), width.max(height)),
+            _ => (width, height),
+        }
+    }
+
+    /// Maps a position onto a `width` by `height` grid, or `None` if it
+    /// borders dead space. Positions off a sphere are only mapped when they
+    /// are one step past a single edge.
+    pub fn wrap(self, x: i64, y: i64, width: i64, height: i64) -> Option<(i64, i64)> {
+        let inside = |x: i64, y: i64| (0..width).contains(&x) && (0..height).contains(&y);
+        if inside(x, y) {
+            return Some((x, y));
+        }
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+
+        let (wrapped_x, wrapped_y) = (x.rem_euclid(width), y.rem_euclid(height));
+        let odd = |turns: i64| turns.rem_euclid(2) == 1;
+        let (x, y) = match self {
+            Topology::Torus => (wrapped_x, wrapped_y),
+            Topology::Plane => return None,
+            Topology::ShiftedTorus(shift) => {
+                let turns = y.div_euclid(height);
+                ((x + turns.wrapping_mul(shift)).rem_euclid(width), wrapped_y)
+            }
+            Topology::KleinBottle if odd(y.div_euclid(height)) => {
+                (width - 1 - wrapped_x, wrapped_y)
+            }
+            Topology::KleinBottle => (wrapped_x, wrapped_y),
+            Topology::CrossSurface => (
+                if odd(y.div_euclid(height)) {
+                    width - 1 - wrapped_x
+                } else {
+                    wrapped_x
+                },
+                if odd(x.div_euclid(width)) {
+                    height - 1 - wrapped_y
+                } else {
+                    wrapped_y
+                },
+            ),
+            Topology::Sphere => match (x, y) {
+                (x, -1) if (0..width).contains(&x) => (0, x),
+                (-1, y) if (0..height).contains(&y) => (y, 0),
+                (x, y) if y == height && (0..width).contains(&x) => (width - 1, x),
+                (x, y) if x == width && (0..height).contains(&y) => (y, height - 1),
+                _ => return None,
+            },
+        };
+        inside(x, y).then_some((x, y))
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    /// Accepts the variant names in kebab case, with `torus+N` or `torus-N`
+    /// for a shifted torus.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        if let Some(shift) = name.strip_prefix("torus").filter(|s| !s.is_empty()) {
+            return shift
+                .strip_prefix('+')
+                .unwrap_or(shift)
+                .parse()
+                .map(Topology::ShiftedTorus)
+                .map_err(|_| format!("invalid torus shift in '{}'", s));
+        }
+        match name.as_str() {
+            "torus" => Ok(Topology::Torus),
+            "plane" | "bounded" => Ok(Topology::Plane),
+            "klein-bottle" | "klein" => Ok(Topology::KleinBottle),
+            "cross-surface" | "cross" => Ok(Topology::CrossSurface),
+            "sphere" => Ok(Topology::Sphere),
+            _ => Err(format!(
+                "unknown topology '{}', expected torus, torus+N, plane, klein-bottle, cross-surface or sphere",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Topology::Torus => write!(f, "torus"),
+            Topology::Plane => write!(f, "plane"),
+            Topology::ShiftedTorus(shift) => write!(f, "torus{:+}", shift),
+            Topology::KleinBottle => write!(f, "klein-bottle"),
+            Topology::CrossSurface => write!(f, "cross-surface"),
+            Topology::Sphere => write!(f, "sphere"),
+        }
+    }
+}
+
+/// Prefix sums of live cells over the grid extended by `range` cells on every
+/// side through the topology, so that the live cells in any rectangle within
+/// reach can be counted with four lookups.
+struct SummedArea {
+    sums: Vec<u64>,
+    stride: usize,
+    range: i32,
+}
+
+impl SummedArea {
+    fn new(universe: &Universe, range: u32) -> Self {
+        let range = range as i32;
+        let (w, h) = (universe.num_cells_x as i32, universe.num_cells_y as i32);
+        let stride = (w + 2 * range + 1) as usize;
+        let mut sums = vec![0; stride * (h + 2 * range + 1) as usize];
+        for y in -range..h + range {
+            let row = (y + range + 1) as usize * stride;
+            let mut row_sum = 0;
+            for x in -range..w + range {
+                row_sum += (universe.state_at(x, y) == 1) as u64;
+                let index = row + (x + range + 1) as usize;
+                sums[index] = sums[index - stride] + row_sum;
+            }
+        }
+        Self {
+            sums,
+            stride,
+            range,
+        }
+    }
+
+    /// Live cells in the rectangle of cells `(x0, y0)` to `(x1, y1)` inclusive.
+    fn count(&self, x0: i32, y0: i32, x1: i32, y1: i32) -> u64 {
+        let index =
+            |x: i32, y: i32| (y + self.range) as usize * self.stride + (x + self.range) as usize;
+        self.sums[index(x1 + 1, y1 + 1)] + self.sums[index(x0, y0)]
+            - self.sums[index(x0, y1 + 1)]
+            - self.sums[index(x1 + 1, y0)]
+    }
+}
+
+/// Hexagonal and triangular grids only count live neighbours, so they can
+/// only run outer totalistic rules.
+fn check_geometry(rule: &Rule, geometry: Geometry) -> Result<(), String> {
+    if geometry != Geometry::Square && (!rule.is_totalistic() || rule.range_rule().is_some()) {
+        return Err(format!(
+            "{} grids only run rules that count the nearest neighbours",
+            geometry
+        ));
+    }
+    Ok(())
+}
+
+/// The simulation state of a finite grid, independent of any window or GPU.
+pub struct Universe {
+    num_cells_x: u32,
+    num_cells_y: u32,
+    rule: Rule,
+    topology: Topology,
+    geometry: Geometry,
+    generation: u64,
+    parallel: bool,
+
+    current_state_data: Vec<u32>,
+    next_state_data: Vec<u32>,
+}
+
+impl Universe {
+    pub fn new(num_cells_x: u32, num_cells_y: u32) -> Self {
+        let len = num_cells_x as usize * num_cells_y as usize;
+        Self {
+            num_cells_x,
+            num_cells_y,
+            rule: Rule::default(),
+            topology: Topology::default(),
+            geometry: Geometry::default(),
+            generation: 0,
+            parallel: true,
+
+            current_state_data: vec![0; len],
+            next_state_data: vec![0; len],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.num_cells_x
+    }
+
+    pub fn height(&self) -> u32 {
+        self.num_cells_y
+    }
+
+    /// Switches how the edges connect, or fails if they can't be joined at
+    /// the current size and geometry.
+    pub fn set_topology(&mut self, topology: Topology) -> Result<(), String> {
+        let (width, height) = (self.num_cells_x, self.num_cells_y);
+        if topology.fit(width, height) != (width, height) {
+            return Err(format!(
+                "a {} needs a square grid, not {}x{}",
+                topology, width, height
+            ));
+        }
+        if !self.geometry.fits(topology, width, height) {
+            return Err(format!(
+                "{} grids can't run on a {} at {}x{}",
+                self.geometry, topology, width, height
+            ));
+        }
+        self.topology = topology;
+        Ok(())
+    }
+
+    /// Row-major cell states, one `u32` per cell.
+    pub fn cells(&self) -> &[u32] {
+        &self.current_state_data
+    }
+
+    fn state_at(&self, x: i32, y: i32) -> u32 {
+        self.position_to_index(x, y)
+            .map_or(0, |index| self.current_state_data[index])
+    }
+
+    /// Computes the next state of the rows starting at `first_row` into `rows`.
+    /// Cells whose whole neighbourhood lies inside the grid are read through
+    /// precomputed index offsets; only the border goes through the topology.
+    fn step_rows(&self, first_row: i32, rows: &mut [u32]) {
+        let (w, h) = (self.num_cells_x as i32, self.num_cells_y as i32);
+        // Offsets for each parity of cell, see `Geometry::parity`.
+        let offsets = [(0, 0), (0, 1)].map(|(x, y)| {
+            self.geometry
+                .neighbours(x, y)
+                .iter()
+                .map(|&(dx, dy)| ((dy * w + dx) as isize, neighbourhood_bit(dx, dy)))
+                .collect::<Vec<_>>()
+        });
+
+        for (row_index, row) in rows.chunks_mut(w.max(1) as usize).enumerate() {
+            let y = first_row + row_index as i32;
+            let interior_row = y >= 1 && y < h - 1;
+            for (x, next) in row.iter_mut().enumerate() {
+                let x = x as i32;
+                let index = (y * w + x) as usize;
+                let neighbourhood: u32 = if interior_row && x >= 1 && x < w - 1 {
+                    offsets[self.geometry.parity(x as i64, y as i64)]
+                        .iter()
+                        .filter(|(offset, _)| {
+                            self.current_state_data[(index as isize + offset) as usize] == 1
+                        })
+                        .map(|(_, bit)| bit)
+                        .sum()
+                } else {
+                    self.geometry
+                        .neighbours(x as i64, y as i64)
+                        .iter()
+                        .filter(|&&(dx, dy)| self.state_at(x + dx, y + dy) == 1)
+                        .map(|&(dx, dy)| neighbourhood_bit(dx, dy))
+                        .sum()
+                };
+                *next = self
+                    .rule
+                    .transition(self.current_state_data[index], neighbourhood);
+            }
+        }
+    }
+
+    /// Like `step_rows`, for a Larger than Life rule whose neighbourhood is
+    /// made up of `blocks`.
+    fn step_range_rows(
+        &self,
+        counts: &SummedArea,
+        blocks: &[Block],
+        first_row: i32,
+        rows: &mut [u32],
+    ) {
+        let w = self.num_cells_x as i32;
+        for (row_index, row) in rows.chunks_mut(w.max(1) as usize).enumerate() {
+            let y = first_row + row_index as i32;
+            for (x, next) in row.iter_mut().enumerate() {
+                let x = x as i32;
+                let count: u64 = blocks
+                    .iter()
+                    .map(|b| b.weight as u64 * counts.count(x + b.x0, y + b.y0, x + b.x1, y + b.y1))
+                    .sum();
+                *next = self
+                    .rule
+                    .next_state(self.current_state_data[(y * w + x) as usize], count as u32);
+            }
+        }
+    }
+
+    /// Like `step_rows`, for a rule loaded from a `.rule` file, which sees
+    /// the full state of each neighbour.
+    fn step_tree_rows(&self, tree: &RuleTree, first_row: i32, rows: &mut [u32]) {
+        let (w, h) = (self.num_cells_x as i32, self.num_cells_y as i32);
+        for (row_index, row) in rows.chunks_mut(w.max(1) as usize).enumerate() {
+            let y = first_row + row_index as i32;
+            for (x, next) in row.iter_mut().enumerate() {
+                let x = x as i32;
+                let interior = y >= 1 && y < h - 1 && x >= 1 && x < w - 1;
+                *next = tree.next_state(|dx, dy| {
+                    if interior {
+                        self.current_state_data[((y + dy) * w + x + dx) as usize]
+                    } else {
+                        self.state_at(x + dx, y + dy)
+                    }
+                });
+            }
+        }
+    }
+
+    /// Maps a cell position to its index according to the topology, or `None`
+    /// if it lies outside a bounded grid.
+    pub fn position_to_index(&self, x: i32, y: i32) -> Option<usize> {
+        let w = self.num_cells_x as i64;
+        let (x, y) = self
+            .topology
+            .wrap(x as i64, y as i64, w, self.num_cells_y as i64)?;
+        Some((y * w + x) as usize)
+    }
+}
+
+impl Engine for Universe {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String> {
+        check_geometry(&rule, self.geometry)?;
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        Some(self.topology)
+    }
+
+    fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    fn set_geometry(&mut self, geometry: Geometry) -> Result<(), String> {
+        check_geometry(&self.rule, geometry)?;
+        let (width, height) = (self.num_cells_x, self.num_cells_y);
+        if !geometry.supports(self.topology) {
+            return Err(format!(
+                "{} grids can't run on a {}",
+                geometry, self.topology
+            ));
+        }
+        if !geometry.fits(self.topology, width, height) {
+            let (fit_width, fit_height) = geometry.fit(self.topology, width, height);
+            return Err(format!(
+                "{} grids on a {} don't line up across the edges at {}x{}, try {}x{}",
+                geometry, self.topology, width, height, fit_width, fit_height
+            ));
+        }
+        self.geometry = geometry;
+        Ok(())
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn get(&self, x: i64, y: i64) -> u32 {
+        self.state_at(x as i32, y as i32)
+    }
+
+    fn set(&mut self, x: i64, y: i64, state: u32) {
+        if let Some(index) = self.position_to_index(x as i32, y as i32) {
+            self.current_state_data[index] = state;
+        }
+    }
+
+    fn step(&mut self) {
+        let width = self.num_cells_x as usize;
+        let mut next_state_data = std::mem::take(&mut self.next_state_data);
+        let range = self
+            .rule
+            .range_rule()
+            .map(|range| (SummedArea::new(self, range.range), range.blocks()));
+        let step_rows = |first_row: i32, rows: &mut [u32]| match (&range, self.rule.tree()) {
+            (Some((counts, blocks)), _) => self.step_range_rows(counts, blocks, first_row, rows),
+            (None, Some(tree)) => self.step_tree_rows(tree, first_row, rows),
+            (None, None) => self.step_rows(first_row, rows),
+        };
+        if self.parallel {
+            let bands = rayon::current_num_threads() * 4;
+            let band_rows = (self.num_cells_y as usize / bands).max(1);
+            next_state_data
+                .par_chunks_mut(band_rows * width.max(1))
+                .enumerate()
+                .for_each(|(band, rows)| step_rows((band * band_rows) as i32, rows));
+        } else {
+            step_rows(0, &mut next_state_data);
+        }
+
+        self.next_state_data = std::mem::replace(&mut self.current_state_data, next_state_data);
+        self.generation += 1;
+    }
+
+    fn live_cells(&self) -> Vec<(i64, i64, u32)> {
+        self.current_state_data
+            .iter()
+            .enumerate()
+            .filter(|(_, &state)| state > 0)
+            .map(|(index, &state)| {
+                let x = index as u32 % self.num_cells_x;
+                let y = index as u32 / self.num_cells_x;
+                (x as i64, y as i64, state)
+            })
+            .collect()
+    }
+
+    fn population(&self) -> u64 {
+        self.current_state_data.iter().filter(|&&s| s > 0).count() as u64
+    }
+
+    fn read_region(&self, rect: Rect, out: &mut [u32]) {
+        let whole_grid = Rect {
+            x: 0,
+            y: 0,
+            width: self.num_cells_x as u64,
+            height: self.num_cells_y as u64,
+        };
+        if rect == whole_grid {
+            out.copy_from_slice(&self.current_state_data);
+            return;
+        }
+        for (index, state) in out.iter_mut().enumerate() {
+            let x = rect.x + (index as u64 % rect.width) as i64;
+            let y = rect.y + (index as u64 / rect.width) as i64;
+            *state = self.get(x, y);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.current_state_data.fill(0);
+    }
+
+    /// Changes the grid dimensions, re-laying the existing cells relative to
+    /// `anchor`. Cells that fall outside the new grid are dropped. The size
+    /// is rounded up if needed for the topology and geometry, see
+    /// [`Topology::fit`] and [`Geometry::fit`].
+    fn resize(&mut self, num_cells_x: u32, num_cells_y: u32, anchor: Anchor) {
+        let (num_cells_x, num_cells_y) = self.topology.fit(num_cells_x, num_cells_y);
+        let (num_cells_x, num_cells_y) = self.geometry.fit(self.topology, num_cells_x, num_cells_y);
+        let (offset_x, offset_y) = match anchor {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Centre => (
+                (num_cells_x as i32 - self.num_cells_x as i32) / 2,
+                (num_cells_y as i32 - self.num_cells_y as i32) / 2,
+            ),
+        };
+
+        let mut state_data = vec![0; num_cells_x as usize * num_cells_y as usize];
+        for (index, &state) in self.current_state_data.iter().enumerate() {
+            let x = (index as u32 % self.num_cells_x) as i32 + offset_x;
+            let y = (index as u32 / self.num_cells_x) as i32 + offset_y;
+            if state > 0
+                && (0..num_cells_x as i32).contains(&x)
+                && (0..num_cells_y as i32).contains(&y)
+            {
+                state_data[(y * num_cells_x as i32 + x) as usize] = state;
+            }
+        }
+
+        self.num_cells_x = num_cells_x;
+        self.num_cells_y = num_cells_y;
+        self.next_state_data = vec![0; state_data.len()];
+        self.current_state_data = state_data;
+    }
+
+    /// Generations are split into row bands across all cores. The result is
+    /// identical either way.
+    fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether every cell's neighbours are of the kind they would be on an
+    /// unbounded grid, and list the cell back as their neighbour.
+    fn neighbours_line_up(universe: &Universe) -> bool {
+        let geometry = universe.geometry;
+        let (w, h) = (universe.width() as i32, universe.height() as i32);
+        let neighbours = |x: i32, y: i32| {
+            geometry
+                .neighbours(x as i64, y as i64)
+                .iter()
+                .filter_map(move |&(dx, dy)| {
+                    let (x, y) = (x + dx, y + dy);
+                    Some((
+                        universe.position_to_index(x, y)?,
+                        geometry.parity(x as i64, y as i64),
+                    ))
+                })
+        };
+        (0..h).all(|y| {
+            (0..w).all(|x| {
+                let index = (y * w + x) as usize;
+                neighbours(x, y).all(|(n, parity)| {
+                    let (nx, ny) = ((n % w as usize) as i32, (n / w as usize) as i32);
+                    geometry.parity(nx as i64, ny as i64) == parity
+                        && neighbours(nx, ny).any(|(m, _)| m == index)
+                })
+            })
+        })
+    }
+
+    #[test]
+    fn wrapped_geometries_need_matching_sizes() {
+        for geometry in [Geometry::Hexagonal, Geometry::Triangular] {
+            for topology in [Topology::Torus, Topology::ShiftedTorus(3), Topology::Plane] {
+                for (width, height) in [(8, 6), (9, 6), (8, 7), (9, 7)] {
+                    let mut universe = Universe::new(width, height);
+                    universe.set_topology(topology).unwrap();
+                    let fits = geometry.fits(topology, width, height);
+                    assert_eq!(universe.set_geometry(geometry).is_ok(), fits);
+                    universe.geometry = geometry;
+                    assert_eq!(
+                        neighbours_line_up(&universe),
+                        fits,
+                        "{geometry} {topology} {width}x{height}"
+                    );
+
+                    // Resizing rounds up to a size that fits.
+                    universe.resize(width, height, Anchor::TopLeft);
+                    assert!(neighbours_line_up(&universe), "{geometry} {topology}");
+                }
+            }
+            let mut universe = Universe::new(8, 8);
+            universe.set_topology(Topology::KleinBottle).unwrap();
+            assert!(universe.set_geometry(geometry).is_err());
+        }
+    }
+
+    #[test]
+    fn neighbours_are_mutual_on_every_topology() {
+        let topologies = [
+            Topology::Torus,
+            Topology::Plane,
+            Topology::ShiftedTorus(3),
+            Topology::KleinBottle,
+            Topology::CrossSurface,
+            Topology::Sphere,
+        ];
+        for topology in topologies {
+            for (width, height) in [(8, 8), (8, 5), (5, 8), (1, 1)] {
+                let mut universe = Universe::new(width, height);
+                let fits = topology.fit(width, height) == (width, height);
+                assert_eq!(universe.set_topology(topology).is_ok(), fits);
+                universe.topology = topology;
+                if fits {
+                    assert!(neighbours_line_up(&universe), "{topology} {width}x{height}");
+                }
+                // Resizing squares up a sphere.
+                universe.resize(width, height, Anchor::TopLeft);
+                assert!(neighbours_line_up(&universe), "{topology} {width}x{height}");
+            }
+        }
+        // The links that used to go one way on an 8x5 sphere.
+        let mut universe = Universe::new(8, 5);
+        universe.topology = Topology::Sphere;
+        assert!(!neighbours_line_up(&universe));
+    }
+
+    /// A `width` by `height` torus filled with a fixed pseudo-random soup,
+    /// about a quarter of it alive.
+    fn soup(width: u32, height: u32) -> Universe {
+        let mut universe = Universe::new(width, height);
+        let mut seed = 1u32;
+        for state in &mut universe.current_state_data {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            *state = (seed >> 30 == 0) as u32;
+        }
+        universe
+    }
+
+    #[test]
+    fn parallel_steps_match_serial_ones() {
+        let rules = ["B3/S23", "B2/S/C3", "B2-a/S12", "R2,C0,M0,S3..6,B4..5,NM"];
+        for rule in rules {
+            let (mut serial, mut parallel) = (soup(97, 61), soup(97, 61));
+            serial.set_rule(rule.parse().unwrap()).unwrap();
+            parallel.set_rule(rule.parse().unwrap()).unwrap();
+            serial.set_parallel(false);
+            for generation in 0..20 {
+                serial.step();
+                parallel.step();
+                assert_eq!(serial.cells(), parallel.cells(), "{rule} at {generation}");
+            }
+            assert!(serial.population() > 0, "{rule}");
+        }
+    }
+
+    #[test]
+    fn range_rules_count_like_brute_force() {
+        let rules = [
+            "R2,C0,M1,S5..9,B6..8,NM",
+            "R3,C0,M0,S3..7,B4..5,NN",
+            "R3,C3,M1,S6..14,B7..10,NC",
+            "R2,C0,M0,S2..4,B3..4,N+",
+            "R2,C0,M1,S3..6,B4..6,N#",
+            "R1,C0,M0,S2..4,B3,NW3210F0123",
+        ];
+        for topology in [Topology::Torus, Topology::Plane, Topology::KleinBottle] {
+            for rule in rules {
+                let mut universe = soup(37, 23);
+                universe.set_topology(topology).unwrap();
+                universe.set_rule(rule.parse().unwrap()).unwrap();
+                let range = universe.rule.range_rule().unwrap().clone();
+                let r = range.range as i32;
+                for generation in 0..5 {
+                    let expected = (0..23)
+                        .flat_map(|y| (0..37).map(move |x| (x, y)))
+                        .map(|(x, y)| {
+                            let count = (-r..=r)
+                                .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+                                .filter(|&(dx, dy)| universe.state_at(x + dx, y + dy) == 1)
+                                .map(|(dx, dy)| range.weight(dx, dy))
+                                .sum();
+                            universe.rule.next_state(universe.state_at(x, y), count)
+                        })
+                        .collect::<Vec<_>>();
+                    universe.step();
+                    assert_eq!(
+                        universe.cells(),
+                        expected,
+                        "{rule} {topology} at {generation}"
+                    );
+                }
+            }
+        }
+    }
+}